@@ -0,0 +1,376 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use crossbeam_channel::{unbounded, Sender};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType, FRAGMENT_DSIZE};
+use crate::drone_builder::RustableDroneBuilder;
+use crate::drone_error::DroneError;
+
+/// A packet that went through a drone, as captured.
+/// at:        time since the drone started running.
+/// direction: whether the packet was coming into the drone or going out of it.
+/// neighbor:  the adjacent node the packet came from or was directed to, None if unknown or sent through the controller.
+/// decision:  what the drone did with the packet.
+/// packet:    a copy of the packet.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaptureRecord {
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub at: Duration,
+    pub direction: Direction,
+    pub neighbor: Option<NodeId>,
+    pub decision: Decision,
+    pub packet: CapturedPacket
+}
+
+/// Direction of a captured packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Incoming,
+    Outgoing
+}
+
+/// What a drone did with a captured packet.
+/// Received: an incoming packet, before any decision is taken.
+/// Sent:     an outgoing packet sent to an adjacent node, forwarded or created by the drone.
+/// Dropped:  an incoming fragment dropped by the pdr, or an outgoing packet tail-dropped by a congested link.
+/// Filtered: an incoming packet filtered out by the PacketFilter.
+/// Shortcut: an outgoing packet sent to the controller through ControllerShortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Decision {
+    Received,
+    Sent,
+    Dropped,
+    Filtered,
+    Shortcut
+}
+
+/// A serializable copy of a [Packet].
+/// Only the first `length` bytes of a fragment's data are kept, the rest is zeroed when converting it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapturedPacket {
+    pub hop_index: usize,
+    pub hops: Vec<NodeId>,
+    pub session_id: u64,
+    pub pack_type: CapturedPacketType
+}
+
+/// A serializable copy of a [PacketType]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapturedPacketType {
+    MsgFragment { fragment_index: u64, total_n_fragments: u64, data: Vec<u8> },
+    Ack { fragment_index: u64 },
+    Nack { fragment_index: u64, nack_type: CapturedNackType },
+    FloodRequest { flood_id: u64, initiator_id: NodeId, path_trace: Vec<(NodeId, CapturedNodeType)> },
+    FloodResponse { flood_id: u64, path_trace: Vec<(NodeId, CapturedNodeType)> }
+}
+
+/// A serializable copy of a [NackType]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapturedNackType {
+    ErrorInRouting(NodeId),
+    DestinationIsDrone,
+    Dropped,
+    UnexpectedRecipient(NodeId)
+}
+
+/// A serializable copy of a [NodeType]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapturedNodeType {
+    Client,
+    Drone,
+    Server
+}
+
+impl From<&Packet> for CapturedPacket {
+    fn from(packet: &Packet) -> Self {
+        let pack_type = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => CapturedPacketType::MsgFragment {
+                fragment_index: fragment.fragment_index,
+                total_n_fragments: fragment.total_n_fragments,
+                data: fragment.data[..(fragment.length as usize).min(FRAGMENT_DSIZE)].to_vec()
+            },
+            PacketType::Ack(ack) => CapturedPacketType::Ack {
+                fragment_index: ack.fragment_index
+            },
+            PacketType::Nack(nack) => CapturedPacketType::Nack {
+                fragment_index: nack.fragment_index,
+                nack_type: nack.nack_type.into()
+            },
+            PacketType::FloodRequest(request) => CapturedPacketType::FloodRequest {
+                flood_id: request.flood_id,
+                initiator_id: request.initiator_id,
+                path_trace: request.path_trace.iter().map(|(id, t)| (*id, (*t).into())).collect()
+            },
+            PacketType::FloodResponse(response) => CapturedPacketType::FloodResponse {
+                flood_id: response.flood_id,
+                path_trace: response.path_trace.iter().map(|(id, t)| (*id, (*t).into())).collect()
+            }
+        };
+
+        CapturedPacket {
+            hop_index: packet.routing_header.hop_index,
+            hops: packet.routing_header.hops.clone(),
+            session_id: packet.session_id,
+            pack_type
+        }
+    }
+}
+
+impl From<CapturedPacket> for Packet {
+    fn from(packet: CapturedPacket) -> Self {
+        let pack_type = match packet.pack_type {
+            CapturedPacketType::MsgFragment { fragment_index, total_n_fragments, data: _data } => {
+                let length = _data.len().min(FRAGMENT_DSIZE);
+                let mut data = [0; FRAGMENT_DSIZE];
+                data[..length].copy_from_slice(&_data[..length]);
+                PacketType::MsgFragment(Fragment { fragment_index, total_n_fragments, length: length as u8, data })
+            }
+            CapturedPacketType::Ack { fragment_index } => {
+                PacketType::Ack(Ack { fragment_index })
+            }
+            CapturedPacketType::Nack { fragment_index, nack_type } => {
+                PacketType::Nack(Nack { fragment_index, nack_type: nack_type.into() })
+            }
+            CapturedPacketType::FloodRequest { flood_id, initiator_id, path_trace } => {
+                let path_trace = path_trace.into_iter().map(|(id, t)| (id, t.into())).collect();
+                PacketType::FloodRequest(FloodRequest { flood_id, initiator_id, path_trace })
+            }
+            CapturedPacketType::FloodResponse { flood_id, path_trace } => {
+                let path_trace = path_trace.into_iter().map(|(id, t)| (id, t.into())).collect();
+                PacketType::FloodResponse(FloodResponse { flood_id, path_trace })
+            }
+        };
+
+        Packet {
+            routing_header: SourceRoutingHeader { hop_index: packet.hop_index, hops: packet.hops },
+            session_id: packet.session_id,
+            pack_type
+        }
+    }
+}
+
+impl From<NackType> for CapturedNackType {
+    fn from(nack_type: NackType) -> Self {
+        match nack_type {
+            NackType::ErrorInRouting(_id) => CapturedNackType::ErrorInRouting(_id),
+            NackType::DestinationIsDrone => CapturedNackType::DestinationIsDrone,
+            NackType::Dropped => CapturedNackType::Dropped,
+            NackType::UnexpectedRecipient(_id) => CapturedNackType::UnexpectedRecipient(_id)
+        }
+    }
+}
+
+impl From<CapturedNackType> for NackType {
+    fn from(nack_type: CapturedNackType) -> Self {
+        match nack_type {
+            CapturedNackType::ErrorInRouting(_id) => NackType::ErrorInRouting(_id),
+            CapturedNackType::DestinationIsDrone => NackType::DestinationIsDrone,
+            CapturedNackType::Dropped => NackType::Dropped,
+            CapturedNackType::UnexpectedRecipient(_id) => NackType::UnexpectedRecipient(_id)
+        }
+    }
+}
+
+impl From<NodeType> for CapturedNodeType {
+    fn from(node_type: NodeType) -> Self {
+        match node_type {
+            NodeType::Client => CapturedNodeType::Client,
+            NodeType::Drone => CapturedNodeType::Drone,
+            NodeType::Server => CapturedNodeType::Server
+        }
+    }
+}
+
+impl From<CapturedNodeType> for NodeType {
+    fn from(node_type: CapturedNodeType) -> Self {
+        match node_type {
+            CapturedNodeType::Client => NodeType::Client,
+            CapturedNodeType::Drone => NodeType::Drone,
+            CapturedNodeType::Server => NodeType::Server
+        }
+    }
+}
+
+/// Where a drone writes the packets it captures.
+/// Records are written as they happen, a sink that fails or is disconnected is ignored.
+///
+/// ```ignore
+/// let drone = RustableDroneBuilder::new(id, controller_send, controller_recv, packet_recv, packet_send)
+///     .capture(CaptureSink::to_file("drone_3.jsonl")?)
+///     .build()?;
+/// ```
+pub struct CaptureSink {
+    target: CaptureTarget,
+    start: Instant
+}
+
+enum CaptureTarget {
+    Channel(Sender<CaptureRecord>),
+    #[cfg(feature = "serde")]
+    JsonLines(std::sync::Mutex<std::io::LineWriter<Box<dyn std::io::Write + Send>>>)
+}
+
+impl CaptureSink {
+    /// sends every record through a channel
+    pub fn channel(sender: Sender<CaptureRecord>) -> Self {
+        Self::new(CaptureTarget::Channel(sender))
+    }
+
+    /// writes every record as a line of JSON
+    #[cfg(feature = "serde")]
+    pub fn json_lines(writer: impl std::io::Write + Send + 'static) -> Self {
+        Self::new(CaptureTarget::JsonLines(std::sync::Mutex::new(std::io::LineWriter::new(Box::new(writer)))))
+    }
+
+    /// creates (or truncates) a file and writes every record to it as a line of JSON
+    #[cfg(feature = "serde")]
+    pub fn to_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::json_lines(std::fs::File::create(path)?))
+    }
+
+    fn new(target: CaptureTarget) -> Self {
+        CaptureSink {
+            target,
+            start: Instant::now()
+        }
+    }
+
+    /// sets the time records are relative to
+    pub(crate) fn start(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// writes a record of a packet
+    pub(crate) fn record(&self, direction: Direction, neighbor: Option<NodeId>, decision: Decision, packet: &Packet) {
+        let record = CaptureRecord {
+            at: self.start.elapsed(),
+            direction,
+            neighbor,
+            decision,
+            packet: packet.into()
+        };
+
+        match &self.target {
+            CaptureTarget::Channel(sender) => {
+                let _ = sender.send(record);
+            }
+            #[cfg(feature = "serde")]
+            CaptureTarget::JsonLines(writer) => {
+                //records are still meaningful if a thread panicked while holding the lock
+                use std::io::Write;
+                let mut writer = writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if let Ok(line) = serde_json::to_string(&record) {
+                    let _ = writeln!(writer, "{}", line);
+                }
+            }
+        }
+    }
+}
+
+/// Feeds the packets a drone received, as recorded in a capture, to a fresh drone at their original times,
+/// and returns what the fresh drone captured, so that its decisions can be compared with the recorded ones.
+/// The drone is built with a channel to every node found in the records, `configure` can set its pdr, settings, filter and seed.
+///
+/// ```ignore
+/// let records = read_capture("drone_3.jsonl")?;
+/// let replayed = replay_capture(3, records.clone(), |builder| builder.pdr(0.1).seed(42))?;
+/// ```
+pub fn replay_capture(
+    id: NodeId,
+    records: Vec<CaptureRecord>,
+    configure: impl FnOnce(RustableDroneBuilder) -> RustableDroneBuilder
+) -> Result<Vec<CaptureRecord>, DroneError> {
+    let neighbors: HashSet<NodeId> = records.iter().filter_map(|record| record.neighbor).collect();
+    let mut packet_send = HashMap::new();
+    let mut packet_recvs = Vec::new();
+    for neighbor in neighbors {
+        let (send, recv) = unbounded();
+        packet_send.insert(neighbor, send);
+        packet_recvs.push(recv);
+    }
+    //every channel stays open until the replay ends
+    let (controller_send, _controller_recv) = unbounded();
+    let (_controller_send, controller_recv) = unbounded();
+    let (_packet_send, packet_recv) = unbounded();
+    let (capture_send, capture_recv) = unbounded();
+
+    let builder = RustableDroneBuilder::new(id, controller_send, controller_recv, packet_recv, packet_send);
+    let mut drone = configure(builder)
+        .capture(CaptureSink::channel(capture_send))
+        .build()?;
+    drone.replay(records);
+
+    drop(drone);
+    Ok(capture_recv.into_iter().collect())
+}
+
+#[cfg(feature = "serde")]
+pub use self::json_lines::*;
+
+#[cfg(feature = "serde")]
+mod json_lines {
+    use std::error::Error;
+    use std::fmt::{Display, Formatter};
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::path::Path;
+    use super::CaptureRecord;
+
+    /// Errors that can occur while reading a capture
+    #[derive(Debug)]
+    pub enum CaptureError {
+        /// the file could not be read
+        Io(std::io::Error),
+        /// a line is not a valid record, lines are numbered from 1
+        Json(usize, serde_json::Error)
+    }
+
+    impl Display for CaptureError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                CaptureError::Io(error) => write!(f, "could not read the capture: {}", error),
+                CaptureError::Json(line, error) => write!(f, "invalid record at line {}: {}", line, error)
+            }
+        }
+    }
+
+    impl Error for CaptureError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                CaptureError::Io(error) => Some(error),
+                CaptureError::Json(_, error) => Some(error)
+            }
+        }
+    }
+
+    impl From<std::io::Error> for CaptureError {
+        fn from(error: std::io::Error) -> Self {
+            CaptureError::Io(error)
+        }
+    }
+
+    /// reads a capture written by [super::CaptureSink::to_file]
+    pub fn read_capture(path: impl AsRef<Path>) -> Result<Vec<CaptureRecord>, CaptureError> {
+        read_json_lines(BufReader::new(File::open(path)?))
+    }
+
+    /// reads records written as lines of JSON, skipping empty lines
+    pub fn read_json_lines(reader: impl BufRead) -> Result<Vec<CaptureRecord>, CaptureError> {
+        let mut records = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line).map_err(|error| CaptureError::Json(i + 1, error))?);
+        }
+        Ok(records)
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use wg_2024::network::NodeId;
+use crate::drone_settings::DroneSettings;
+use crate::fault_schedule::FaultSchedule;
+use crate::packets_filter::PacketFilter;
+
+/// Settings, filter and fault schedule of a single drone, as read from a configuration file.
+/// Missing fields take their default value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DroneProfile {
+    pub settings: DroneSettings,
+    pub filter: PacketFilter,
+    pub schedule: FaultSchedule
+}
+
+/// Layout of a configuration file: profiles live in the `rustable_drone` table, keyed by NodeId,
+/// so they can share the file with the network configuration, whose tables are ignored.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ProfilesFile {
+    #[serde(with = "node_id_map")]
+    rustable_drone: HashMap<NodeId, DroneProfile>
+}
+
+/// Errors that can occur while loading drone profiles
+#[derive(Debug)]
+pub enum ConfigError {
+    /// the file could not be read
+    Io(std::io::Error),
+    /// the file is not valid TOML, or does not match the profiles layout
+    Toml(toml::de::Error),
+    /// the file is not valid JSON, or does not match the profiles layout
+    Json(serde_json::Error)
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "could not read the configuration file: {}", error),
+            ConfigError::Toml(error) => write!(f, "invalid TOML configuration: {}", error),
+            ConfigError::Json(error) => write!(f, "invalid JSON configuration: {}", error)
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Reads the drone profiles from a file, keyed by NodeId.
+/// Files with a `.json` extension are read as JSON, every other file as TOML.
+///
+/// ```toml
+/// [rustable_drone.3.settings]
+/// log_to_stdout = true
+/// latency = { Uniform = { min = "10ms", max = "30ms" } }
+///
+/// [rustable_drone.3.filter]
+/// list = [4, 5]
+/// filter_type = "BlackList"
+///
+/// [[rustable_drone.3.schedule]]
+/// at = "3s"
+/// action = { SetPacketDropRate = 0.8 }
+/// ```
+pub fn load_profiles(path: impl AsRef<Path>) -> Result<HashMap<NodeId, DroneProfile>, ConfigError> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(ConfigError::Io)?;
+
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        profiles_from_json(&content)
+    } else {
+        profiles_from_toml(&content)
+    }
+}
+
+/// Reads the drone profiles from a TOML string, keyed by NodeId
+pub fn profiles_from_toml(content: &str) -> Result<HashMap<NodeId, DroneProfile>, ConfigError> {
+    let file: ProfilesFile = toml::from_str(content).map_err(ConfigError::Toml)?;
+    Ok(file.rustable_drone)
+}
+
+/// Reads the drone profiles from a JSON string, keyed by NodeId
+pub fn profiles_from_json(content: &str) -> Result<HashMap<NodeId, DroneProfile>, ConfigError> {
+    let file: ProfilesFile = serde_json::from_str(content).map_err(ConfigError::Json)?;
+    Ok(file.rustable_drone)
+}
+
+/// (De)serializes maps keyed by NodeId with string keys, as TOML tables only allow those
+pub(crate) mod node_id_map {
+    use std::collections::HashMap;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use wg_2024::network::NodeId;
+
+    pub fn serialize<V: Serialize, S: Serializer>(map: &HashMap<NodeId, V>, serializer: S) -> Result<S::Ok, S::Error> {
+        let map: HashMap<String, &V> = map.iter().map(|(id, value)| (id.to_string(), value)).collect();
+        map.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<NodeId, V>, D::Error> {
+        let map: HashMap<String, V> = HashMap::deserialize(deserializer)?;
+        map.into_iter()
+            .map(|(id, value)| {
+                id.parse::<NodeId>()
+                    .map(|id| (id, value))
+                    .map_err(|_| D::Error::custom(format!("invalid NodeId: {}", id)))
+            })
+            .collect()
+    }
+}
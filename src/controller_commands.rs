@@ -1,29 +1,68 @@
 use std::time::Duration;
+use crossbeam_channel::Sender;
 use wg_2024::controller::DroneCommand;
 use wg_2024::network::NodeId;
+use crate::drone_settings::{DropRateLink, ErrorPolicy, RateLimit, Reorder, SchedulingPolicy};
+use crate::fault_schedule::FaultSchedule;
+use crate::latency::LatencyDistribution;
+use crate::filter_rules::{FilterRule, RuleId};
+use crate::loss_model::{LossModel, LossModelType};
+use crate::packet_kind::PacketKindMask;
 use crate::packets_filter::FilterType;
+use crate::payload_mutation::PayloadMutation;
+use crate::stats::DroneStats;
 
 pub enum RustableCommand {
     DroneCommand(DroneCommand),
     SettingCommand(SettingsCommand),
     FilterCommand(FilterCommand),
-    Quack
+    Quack,
+    SetLinkDropRate(NodeId, f32),
+    ResetLinkDropRate(NodeId),
+    SetLossModel(Box<dyn LossModel>),
+    SetSchedule(FaultSchedule),
+    QueryStats(Sender<DroneStats>)
 }
 
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SettingsCommand {
     LogToStdout(bool),
-    SleepDuration(Duration),
+    SleepDuration(#[cfg_attr(feature = "serde", serde(with = "humantime_serde"))] Duration),
     AwaitQueuedPacketsOnCrash(bool),
     FilterPackets(bool),
-    SendNackOnFilteredPackets(bool)
+    SendNackOnFilteredPackets(bool),
+    ErrorPolicy(ErrorPolicy),
+    FloodCacheCapacity(usize),
+    FloodCacheTtl(#[cfg_attr(feature = "serde", serde(with = "humantime_serde"))] Option<Duration>),
+    DropRateLink(DropRateLink),
+    LossModel(LossModelType),
+    Seed(u64),
+    PayloadMutation(Option<PayloadMutation>),
+    DuplicateProbability(f32),
+    Reorder(Option<Reorder>),
+    DisorderAcksAndNacks(bool),
+    Latency(LatencyDistribution),
+    LinkLatency(NodeId, Option<LatencyDistribution>),
+    RateLimit(Option<RateLimit>),
+    LinkRateLimit(NodeId, Option<RateLimit>),
+    Scheduling(SchedulingPolicy)
 }
 
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilterCommand {
     AddId(NodeId),
     RemoveId(NodeId),
     Clear,
     Set(Vec<NodeId>),
     SetType(FilterType),
+    AddIdWithKinds(NodeId, PacketKindMask),
+    SetKinds(PacketKindMask),
+    AddRule(FilterRule),
+    InsertRule(usize, FilterRule),
+    RemoveRule(RuleId),
+    ClearRules,
 }
 
 impl From<DroneCommand> for RustableCommand {
@@ -0,0 +1,73 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::Instant;
+
+/// Holds items until their deadline passes.
+/// Items with the same deadline are released in insertion order.
+pub struct DelayQueue<T> {
+    heap: BinaryHeap<Delayed<T>>,
+    next_seq: u64
+}
+
+/// An item waiting in a [DelayQueue]
+struct Delayed<T> {
+    deadline: Instant,
+    seq: u64,
+    item: T
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> Self {
+        DelayQueue {
+            heap: BinaryHeap::new(),
+            next_seq: 0
+        }
+    }
+}
+
+impl<T> DelayQueue<T> {
+    /// adds an item, to be released once the deadline passes
+    pub fn push(&mut self, deadline: Instant, item: T) {
+        self.heap.push(Delayed { deadline, seq: self.next_seq, item });
+        self.next_seq += 1;
+    }
+
+    /// returns the earliest deadline in the queue
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.heap.peek().map(|d| d.deadline)
+    }
+
+    /// removes and returns the earliest item whose deadline is not after `now`
+    pub fn pop_due(&mut self, now: Instant) -> Option<T> {
+        if self.heap.peek()?.deadline > now {
+            return None;
+        }
+        self.heap.pop().map(|d| d.item)
+    }
+
+    /// removes and returns the earliest item, regardless of its deadline
+    pub fn pop(&mut self) -> Option<T> {
+        self.heap.pop().map(|d| d.item)
+    }
+}
+
+impl<T> PartialEq for Delayed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline && self.seq == other.seq
+    }
+}
+
+impl<T> Eq for Delayed<T> {}
+
+impl<T> PartialOrd for Delayed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Delayed<T> {
+    //reversed, so that the max-heap pops the earliest deadline first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
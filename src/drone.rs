@@ -1,18 +1,33 @@
 use std::collections::HashMap;
-use std::mem::uninitialized;
-use std::thread;
-use crossbeam_channel::{select_biased, Receiver, RecvError, SendError, Sender};
-use rand::{thread_rng, Rng};
+use std::time::{Duration, Instant};
+use crossbeam_channel::{at, never, select_biased, Receiver, RecvError, Sender};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use wg_2024::controller::{DroneCommand, DroneEvent};
 use wg_2024::drone::{Drone};
 use wg_2024::network::{NodeId, SourceRoutingHeader};
-use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, Packet, PacketType};
 use wg_2024::packet::NackType::{DestinationIsDrone, Dropped, ErrorInRouting, UnexpectedRecipient};
 use wg_2024::packet::NodeType::*;
 use wg_2024::packet::PacketType::MsgFragment;
+use crate::capture::{CaptureRecord, CaptureSink, Decision, Direction};
 use crate::controller_commands::{FilterCommand, RustableCommand, SettingsCommand};
-use crate::drone_settings::DroneSettings;
+use crate::delay_queue::DelayQueue;
+use crate::drone_builder::RustableDroneBuilder;
+use crate::drone_error::DroneError;
+use crate::drone_settings::{DropRateLink, DroneSettings, ErrorPolicy, RateLimit, SchedulingPolicy};
+use crate::fault_schedule::{FaultAction, FaultSchedule, MAX_FAULT_TIME};
+use crate::filter_rules::RuleAction;
+use crate::flood_cache::FloodIdCache;
+use crate::latency::{LatencyDistribution, MAX_LATENCY};
+use crate::log::debug_field;
+use crate::loss_model::{LossModel, LossModelType};
+use crate::packet_kind::PacketKind;
 use crate::packets_filter::PacketFilter;
+use crate::payload_mutation::{MutationMode, PayloadMutation};
+use crate::rate_limiter::{Admission, RateLimiter};
+use crate::scheduler::PacketScheduler;
+use crate::stats::StatsHandle;
 
 
 
@@ -20,13 +35,34 @@ pub struct RustableDrone {
     id: NodeId,
     controller_send: Sender<DroneEvent>,
     controller_recv: Receiver<DroneCommand>,
+    rustable_recv: Receiver<RustableCommand>,
+    event_sink: Option<Sender<DroneEvent>>,
+    capture: Option<CaptureSink>,
     packet_send: HashMap<NodeId, Sender<Packet>>,
     packet_recv: Receiver<Packet>,
+    scheduler: PacketScheduler,
     drop_rate: f32,
     pub settings: DroneSettings,
     pub filter: PacketFilter,
-    flood_ids: HashMap<NodeId, Vec<u64>>,
-    has_to_crash: bool
+    flood_ids: FloodIdCache,
+    delayed: DelayQueue<(NodeId, Packet)>,
+    pending_schedule: FaultSchedule,
+    faults: DelayQueue<FaultAction>,
+    rate_limiter: RateLimiter<Packet>,
+    loss_model: Box<dyn LossModel>,
+    loss_model_type: Option<LossModelType>,
+    rng: StdRng,
+    stats: StatsHandle,
+    state: DroneState
+}
+
+/// Lifecycle of a drone.
+/// Running:  packets and commands are handled normally.
+/// Crashing: a crash command was received, queued packets are handled as the protocol states for a crashing drone
+///           until every sender has been removed or the packet channel disconnects.
+enum DroneState {
+    Running,
+    Crashing
 }
 
 impl Drone for RustableDrone {
@@ -38,42 +74,97 @@ impl Drone for RustableDrone {
         packet_send: HashMap<NodeId, Sender<Packet>>,
         pdr: f32
     ) -> Self {
-        if pdr < 0.0f32 || pdr > 1.0f32 {
-            panic!("Invalid packet drop rate value")
-        }
-
-        Self {
-            id,
-            controller_send,
-            controller_recv,
-            packet_send,
-            packet_recv,
-            drop_rate: pdr,
-            settings: DroneSettings::default(),
-            filter: PacketFilter::default(),
-            flood_ids: HashMap::new(),
-            has_to_crash: false
-        }
+        RustableDroneBuilder::new(id, controller_send, controller_recv, packet_recv, packet_send)
+            .pdr(pdr)
+            .build()
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
     fn run(&mut self) {
+        self.apply_settings();
+        #[cfg(feature = "tracing")]
+        let mut stdout_subscriber = crate::log::StdoutSubscriber::default();
+
         loop {
-            if self.has_to_crash {
-                if !self.settings.await_queued_packets_on_crash {
-                    return
-                }
-                //send all remaining packets
-                while let Ok(packet) = self.packet_recv.try_recv() {
-                    self.packet_handler(Ok(packet));
+            #[cfg(feature = "tracing")]
+            stdout_subscriber.sync(self.settings.log_to_stdout);
+            match self.state {
+                DroneState::Running => {
+                    let timer = self.timer();
+                    //fires right away while received packets are waiting in the scheduler
+                    let ready = if self.scheduler.is_empty() { never() } else { at(Instant::now()) };
+
+                    // listens to commands/timer/packets pipes, prioritizing commands
+                    select_biased! {
+                        recv(self.controller_recv) -> command => {
+                            self.command_handler(command)
+                        },
+                        recv(self.rustable_recv) -> command => {
+                            self.rustable_command_handler(command)
+                        },
+                        recv(timer) -> _ => {
+                            self.run_due_faults();
+                            self.release_delayed_packets();
+                            self.release_rate_limited_packets()
+                        },
+                        recv(self.packet_recv) -> packet => {
+                            match packet {
+                                Ok(packet) => {
+                                    self.schedule_packet(packet);
+                                    self.serve_packet()
+                                },
+                                Err(_) => {
+                                    //nothing will ever be received again
+                                    while !self.scheduler.is_empty() {
+                                        self.serve_packet();
+                                    }
+                                    self.flush_delayed_packets();
+                                    self.handle_error(DroneError::PacketChannelDisconnected, None);
+                                    return
+                                }
+                            }
+                        },
+                        recv(ready) -> _ => {
+                            self.serve_packet()
+                        }
+                    }
                 }
-            } else {
-                // listens to commands/packets pipes, prioritizing commands
-                select_biased! {
-                    recv(self.controller_recv) -> command => {
-                        self.command_handler(command)
-                    },
-                     recv(self.packet_recv) -> packet => {
-                        self.packet_handler(packet)
+                DroneState::Crashing => {
+                    //queued packets are not awaited or every sender has been removed, nothing is left to do but held packets
+                    if !self.settings.await_queued_packets_on_crash || self.packet_send.is_empty() {
+                        self.flush_delayed_packets();
+                        return
+                    }
+
+                    //packets received before the crash are handled first
+                    if let Some(packet) = self.scheduler.pop(self.settings.scheduling) {
+                        self.crashing_packet_handler(packet);
+                        continue
+                    }
+
+                    let timer = self.timer();
+
+                    // keeps handling packets until the neighbors stop sending them
+                    select_biased! {
+                        recv(self.controller_recv) -> command => {
+                            self.crashing_command_handler(command)
+                        },
+                        recv(self.rustable_recv) -> command => {
+                            self.crashing_rustable_command_handler(command)
+                        },
+                        recv(timer) -> _ => {
+                            self.release_delayed_packets();
+                            self.release_rate_limited_packets()
+                        },
+                        recv(self.packet_recv) -> packet => {
+                            match packet {
+                                Ok(packet) => self.crashing_packet_handler(packet),
+                                Err(_) => {
+                                    self.flush_delayed_packets();
+                                    return
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -83,158 +174,273 @@ impl Drone for RustableDrone {
 
 
 impl RustableDrone {
-    /// Handles a Packet
-    fn packet_handler(&mut self, packet: Result<Packet, RecvError>) {
-        if packet.is_err() {
-            panic!("Packet Error in RustableDrone: {}\n{}", self.id, packet.err().unwrap());
+    /// Creates a drone from a builder whose values have already been checked
+    /// `settings_loss_model` is the model built from the settings, used unless the builder has a custom one
+    pub(crate) fn from_builder(builder: RustableDroneBuilder, settings_loss_model: Box<dyn LossModel>) -> Self {
+        let settings = builder.settings;
+        let flood_ids = FloodIdCache::new(settings.flood_cache_capacity, settings.flood_cache_ttl);
+        let (loss_model, loss_model_type) = match builder.loss_model {
+            Some(loss_model) => (loss_model, None),
+            None => (settings_loss_model, Some(settings.loss_model.clone()))
+        };
+        let rng = Self::new_rng(settings.seed);
+
+        Self {
+            id: builder.id,
+            controller_send: builder.controller_send,
+            controller_recv: builder.controller_recv,
+            rustable_recv: builder.command_channel.unwrap_or_else(never),
+            event_sink: builder.event_sink,
+            capture: builder.capture,
+            packet_send: builder.packet_send,
+            packet_recv: builder.packet_recv,
+            scheduler: PacketScheduler::default(),
+            drop_rate: builder.pdr,
+            settings,
+            filter: builder.filter,
+            flood_ids,
+            delayed: DelayQueue::default(),
+            pending_schedule: builder.schedule,
+            faults: DelayQueue::default(),
+            rate_limiter: RateLimiter::default(),
+            loss_model,
+            loss_model_type,
+            rng,
+            stats: StatsHandle::default(),
+            state: DroneState::Running
         }
+    }
 
-        let packet: Packet = packet.unwrap();
+    /// Sets the channel the drone receives [RustableCommand]s from, alongside the [DroneCommand]s coming from the controller
+    pub fn with_command_channel(mut self, rustable_recv: Receiver<RustableCommand>) -> Self {
+        self.rustable_recv = rustable_recv;
+        self
+    }
 
-        //indexing errors
-        if packet.routing_header.hop_index < 1 && !matches!(&packet.pack_type, PacketType::FloodRequest(x)) {
-            panic!("Indexing Error in RustableDrone {} receiving a Packet from node {}. hop_index is less than 1 in packet's header:\n{:?}", self.id, packet.routing_header.previous_hop().unwrap(), packet.routing_header);
-        }
-        if packet.routing_header.hop_index > packet.routing_header.hops.len() && !matches!(&packet.pack_type, PacketType::FloodRequest(x)) {
-            panic!("Indexing Error in RustableDrone {} receiving a Packet from node {}. hop_index is bigger than hops len in packet's header:\n{:?}", self.id, packet.routing_header.previous_hop().unwrap(), packet);
+    /// Returns a handle to the drone's statistics, which stays up to date while the drone runs
+    pub fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    /// Handles the packets received in a capture at their original times, instead of the ones coming from the packet channel,
+    /// then sends every held packet right away
+    pub(crate) fn replay(&mut self, records: Vec<CaptureRecord>) {
+        self.apply_settings();
+        let start = Instant::now();
+
+        let received = records.into_iter()
+            .filter(|record| record.direction == Direction::Incoming && record.decision == Decision::Received);
+        for record in received {
+            //delayed packets, rate limited packets and faults keep being released while waiting
+            let due = at(start + record.at);
+            loop {
+                let timer = self.timer();
+                let packet_due = select_biased! {
+                    recv(timer) -> _ => false,
+                    recv(due) -> _ => true
+                };
+                if packet_due {
+                    break
+                }
+                self.run_due_faults();
+                self.release_delayed_packets();
+                self.release_rate_limited_packets();
+            }
+
+            self.schedule_packet(record.packet.into());
+            self.serve_packet();
         }
 
-        match packet.clone().pack_type {
-            PacketType::Nack(_nack) => self.nack_handler(&packet, _nack),
-            PacketType::Ack(_ack) => self.ack_handler(&packet, _ack),
-            PacketType::MsgFragment(_frag) => self.msg_fragment_handler(&packet, _frag),
-            PacketType::FloodRequest(_req) => self.flood_req_handler(&packet, _req),
-            PacketType::FloodResponse(_res) => self.flood_res_handler(&packet),
+        self.flush_delayed_packets();
+    }
+
+    /// Creates a random number generator from a seed, or from a random one if None
+    fn new_rng(seed: Option<u64>) -> StdRng {
+        match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy()
         }
     }
 
-    fn msg_fragment_handler(&self, packet: &Packet, mut fragment: Fragment) {
-        let from: NodeId = packet.routing_header.previous_hop().unwrap();
+    /// Brings the internal state in line with the settings, which might have been changed through the pub field
+    fn apply_settings(&mut self) {
+        if let Some(seed) = self.settings.seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
 
-        //destination is drone
-        if packet.routing_header.is_last_hop() {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered a DestinationIsDrone error while receiving a MsgFragment from node {}", self.id, from);
-                }
-                self.send_nack(from, packet, fragment.fragment_index, DestinationIsDrone);
-            } else {
-                panic!("RustableDrone {} encountered a DestinationIsDrone error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
+        self.flood_ids.set_capacity(self.settings.flood_cache_capacity);
+        self.flood_ids.set_ttl(self.settings.flood_cache_ttl);
+
+        //a custom loss model is only replaced through a command
+        if self.loss_model_type.as_ref().is_some_and(|t| *t != self.settings.loss_model) {
+            if let Err(error) = self.set_loss_model_type(self.settings.loss_model.clone()) {
+                drone_error!(self, error = error.to_string(); "received an invalid loss model: {}", error);
             }
-            return;
         }
 
-        let to: NodeId = packet.routing_header.next_hop().unwrap();
+        let schedule = std::mem::take(&mut self.pending_schedule);
+        self.start_schedule(schedule);
 
-        //drop probability
-        if thread_rng().gen_bool(self.drop_rate as f64) {
-            //sends dropped nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} dropped a MsgFragment received from node {} directed to node {}", self.id, from, to);
-                }
-                self.send_nack(from, packet, fragment.fragment_index, Dropped);
-            } else {
-                panic!("RustableDrone {} dropped a packet but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-            }
-            return;
+        if let Some(capture) = &mut self.capture {
+            capture.start();
         }
+    }
 
-        //filter
-        if self.settings.filter_packets && !self.filter.is_allowed(from) {
-            if self.settings.log_to_stdout {
-                println!("RustableDrone {} filtered a MsgFragment received from node {} directed to node {}", self.id, from, to)
-            }
-            if self.settings.send_nack_on_filtered_packet {
-                //sends dropped nack
-                if self.packet_send.contains_key(&from) {
-                    self.send_nack(from, packet, fragment.fragment_index, Dropped);
-                } else {
-                    panic!("RustableDrone {} filtered a packet but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-                }
+    /// Replaces the running fault schedule with a new one, whose times start now
+    fn start_schedule(&mut self, schedule: FaultSchedule) {
+        let start = Instant::now();
+        self.faults = DelayQueue::default();
+        for fault in schedule {
+            //times are capped like latencies, a huge one read from a file can't overflow the clock
+            if let Some(deadline) = start.checked_add(fault.at.min(MAX_FAULT_TIME)) {
+                self.faults.push(deadline, fault.action);
             }
-            return;
         }
+    }
 
-        //routing error
-        if !self.packet_send.contains_key(&to) {
-            //sends routing error nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an ErrorInRouting while trying to forward a MsgFragment from node {} to node {}", self.id, from, to);
-                }
-                self.send_nack(from, packet, fragment.fragment_index, ErrorInRouting(to));
-            } else {
-                panic!("RustableDrone {} encountered an ErrorInRouting while trying to forward a MsgFragment to node {} but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, to, from);
-            }
-            return;
+    /// Performs every scheduled fault whose time has come
+    fn run_due_faults(&mut self) {
+        while let Some(action) = self.faults.pop_due(Instant::now()) {
+            //log it
+            drone_log!(info, self, fault = debug_field(&action); "performed the scheduled fault {:?}", action);
+            self.execute_command(RustableCommand::from(action));
         }
+    }
 
-        //unexpected recipient
-        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an UnexpectedRecipient error while trying to forward a MsgFragment from node {} to node {}", self.id, from, to);
+    /// Replaces the loss model with a new one of the specified type.
+    /// If the type is invalid the current model is kept, and the settings are restored to match it
+    fn set_loss_model_type(&mut self, loss_model: LossModelType) -> Result<(), DroneError> {
+        match loss_model.build() {
+            Ok(model) => {
+                self.loss_model = model;
+                self.loss_model_type = Some(loss_model.clone());
+                self.settings.loss_model = loss_model;
+                Ok(())
+            }
+            Err(error) => {
+                if let Some(current) = &self.loss_model_type {
+                    self.settings.loss_model = current.clone();
                 }
-                self.send_nack(from, packet, fragment.fragment_index, UnexpectedRecipient(self.id));
-            } else {
-                panic!("RustableDrone {} encountered an UnexpectedRecipient error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
+                Err(error)
             }
-            return;
         }
+    }
 
-        //is quack
-        if self.settings.quack {
-            let bytes = "QUACK".as_bytes();
-            let bytes_len = bytes.len();
-            for i in 0..fragment.data.len() {
-                fragment.data[i] = bytes[i%bytes_len];
+    /// Adds a received packet to the scheduler, along with every other packet waiting in the channel unless the policy is Fifo
+    fn schedule_packet(&mut self, packet: Packet) {
+        self.scheduler.push(packet);
+        if self.settings.scheduling != SchedulingPolicy::Fifo {
+            //the policy chooses among every waiting packet
+            while let Ok(packet) = self.packet_recv.try_recv() {
+                self.scheduler.push(packet);
             }
         }
+    }
 
-        //sleeps
-        if !self.settings.sleep_duration.is_zero() {
-            thread::sleep(self.settings.sleep_duration);
+    /// Handles the next packet chosen by the scheduler
+    fn serve_packet(&mut self) {
+        if let Some(packet) = self.scheduler.pop(self.settings.scheduling) {
+            self.packet_handler(packet);
         }
+    }
 
-        //all good, propagate packet as it should be
-        let mut header = packet.routing_header.clone();
-        header.hop_index += 1;
-        let res: Result<(), SendError<Packet>> = self.packet_send.get(&to).unwrap().send(
-            Packet{
-                routing_header: header,
-                session_id: packet.session_id,
-                pack_type: (MsgFragment(fragment)),
+    /// Handles a Packet, applying the error policy if something goes wrong
+    fn packet_handler(&mut self, packet: Packet) {
+        self.count_received(&packet);
+        if let Err(error) = self.route_packet(&packet) {
+            self.handle_error(error, Some(&packet));
+        }
+    }
+
+    /// Handles a Packet received while crashing, applying the error policy if something goes wrong
+    fn crashing_packet_handler(&mut self, packet: Packet) {
+        self.count_received(&packet);
+        let res = match &packet.pack_type {
+            PacketType::FloodRequest(_) => {
+                //log it
+                drone_log!(info, self, packet_type = "FloodRequest", session_id = packet.session_id; "is crashing and dropped a FloodRequest");
+                Ok(())
+            }
+            PacketType::MsgFragment(fragment) => {
+                //sends routing error nack, as this drone is no longer part of the network
+                self.check_hop_index(&packet).and_then(|_| {
+                    let from: NodeId = packet.routing_header.previous_hop().unwrap();
+                    drone_log!(info, self, from = from, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment.fragment_index, nack_type = "ErrorInRouting"; "is crashing and sent an ErrorInRouting back to node {}", from);
+                    //the sender might have been removed before its queued fragments were handled
+                    self.send_nack_or_shortcut(from, &packet, fragment.fragment_index, ErrorInRouting(self.id))
+                })
             }
-        );
+            //acks, nacks and flood responses still have to reach their destination
+            _ => self.route_packet(&packet)
+        };
 
-        if res.is_err() {
-            panic!("{}", res.err().unwrap())
+        if let Err(error) = res {
+            self.handle_error(error, Some(&packet));
         }
+    }
 
-        //log it
-        if self.settings.log_to_stdout {
-            println!("RustableDrone {} received a MsgFragment from node {} and forwarded it to node {}", self.id, from, to);
+    /// Counts a received packet in the statistics and captures it
+    fn count_received(&self, packet: &Packet) {
+        //the node the packet comes from, if the header is not malformed
+        let from = match &packet.pack_type {
+            PacketType::FloodRequest(request) => Some(Self::flood_sender(request)),
+            _ => packet.routing_header.hop_index.checked_sub(1).and_then(|i| packet.routing_header.hops.get(i).copied())
+        };
+        self.stats.update(|stats| {
+            stats.received(PacketKind::of(&packet.pack_type), from);
+            if matches!(packet.pack_type, PacketType::FloodRequest(_)) {
+                stats.flood_requests_seen += 1;
+            }
+        });
+        self.capture(Direction::Incoming, from, Decision::Received, packet);
+    }
+
+    /// Records a packet in the capture sink, if there is one
+    fn capture(&self, direction: Direction, neighbor: Option<NodeId>, decision: Decision, packet: &Packet) {
+        if let Some(capture) = &self.capture {
+            capture.record(direction, neighbor, decision, packet);
         }
     }
 
-    fn nack_handler(&self, packet: &Packet, nack: Nack) {
+    /// Checks the packet's header and passes it to the handler of its type
+    fn route_packet(&mut self, packet: &Packet) -> Result<(), DroneError> {
+        //indexing errors
+        if !matches!(&packet.pack_type, PacketType::FloodRequest(_)) {
+            self.check_hop_index(packet)?;
+        }
+
+        match packet.clone().pack_type {
+            PacketType::Nack(_nack) => self.nack_handler(packet, _nack),
+            PacketType::Ack(_ack) => self.ack_handler(packet, _ack),
+            PacketType::MsgFragment(_frag) => self.msg_fragment_handler(packet, _frag),
+            PacketType::FloodRequest(_req) => self.flood_req_handler(packet, _req),
+            PacketType::FloodResponse(_res) => self.flood_res_handler(packet),
+        }
+    }
+
+    /// Checks that the hop_index of the packet's routing header is in bounds ( >= 1 and < len )
+    fn check_hop_index(&self, packet: &Packet) -> Result<(), DroneError> {
+        if packet.routing_header.hop_index < 1 || packet.routing_header.hop_index >= packet.routing_header.hops.len() {
+            return Err(DroneError::InvalidHopIndex(packet.routing_header.clone()));
+        }
+        Ok(())
+    }
+
+    fn msg_fragment_handler(&mut self, packet: &Packet, mut fragment: Fragment) -> Result<(), DroneError> {
         let from: NodeId = packet.routing_header.previous_hop().unwrap();
 
+        //unexpected recipient
+        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
+            //sends unexpected recipient nack
+            drone_log!(warn, self, from = from, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment.fragment_index, nack_type = "UnexpectedRecipient"; "encountered an UnexpectedRecipient error while receiving a MsgFragment from node {}", from);
+            return self.send_nack_or_shortcut(from, packet, fragment.fragment_index, UnexpectedRecipient(self.id));
+        }
+
         //destination is drone
         if packet.routing_header.is_last_hop() {
             //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered a DestinationIsDrone error while receiving a NACK from node {}", self.id, from);
-                }
-                self.send_nack_through_controller(packet, nack.fragment_index, DestinationIsDrone);
-            } else {
-                panic!("RustableDrone {} encountered a DestinationIsDrone error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-            }
-            return;
+            drone_log!(warn, self, from = from, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment.fragment_index, nack_type = "DestinationIsDrone"; "encountered a DestinationIsDrone error while receiving a MsgFragment from node {}", from);
+            return self.send_nack_or_shortcut(from, packet, fragment.fragment_index, DestinationIsDrone);
         }
 
         let to: NodeId = packet.routing_header.next_hop().unwrap();
@@ -242,328 +448,337 @@ impl RustableDrone {
         //routing error
         if !self.packet_send.contains_key(&to) {
             //sends routing error nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an ErrorInRouting while trying to forward a NACK from node {} to node {}", self.id, from, to);
-                }
-                self.send_nack_through_controller(packet, nack.fragment_index, ErrorInRouting(to));
-            } else {
-                panic!("RustableDrone {} encountered an ErrorInRouting while trying to forward a NACK to node {} but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, to, from);
-            }
-            return;
+            drone_log!(warn, self, from = from, to = to, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment.fragment_index, nack_type = "ErrorInRouting"; "encountered an ErrorInRouting while trying to forward a MsgFragment from node {} to node {}", from, to);
+            return self.send_nack_or_shortcut(from, packet, fragment.fragment_index, ErrorInRouting(to));
         }
 
-        //unexpected recipient
-        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an UnexpectedRecipient error while trying to forward a NACK from node {} to node {}", self.id, from, to);
+        //drop probability
+        let pdr = self.link_drop_rate(from, to);
+        if self.loss_model.should_drop(pdr, &mut self.rng) {
+            //sends dropped nack
+            drone_log!(info, self, from = from, to = to, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment.fragment_index; "dropped a MsgFragment received from node {} directed to node {}", from, to);
+            self.stats.update(|stats| stats.dropped(PacketKind::MsgFragment, Some(from)));
+            self.capture(Direction::Incoming, Some(from), Decision::Dropped, packet);
+            self.send_event(DroneEvent::PacketDropped(packet.clone()))?;
+            return self.send_nack_or_shortcut(from, packet, fragment.fragment_index, Dropped);
+        }
+
+        //filter
+        let mut extra_delay = Duration::ZERO;
+        match self.filter_action(packet, from, Some(to)) {
+            RuleAction::Allow => {}
+            action @ (RuleAction::Drop | RuleAction::DropWithNack) => {
+                drone_log!(info, self, from = from, to = to, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment.fragment_index; "filtered a MsgFragment received from node {} directed to node {}", from, to);
+                self.send_event(DroneEvent::PacketDropped(packet.clone()))?;
+                if action == RuleAction::DropWithNack {
+                    //sends dropped nack
+                    return self.send_nack_or_shortcut(from, packet, fragment.fragment_index, Dropped);
                 }
-                self.send_nack_through_controller(packet, nack.fragment_index, UnexpectedRecipient(self.id));
-            } else {
-                panic!("RustableDrone {} encountered an UnexpectedRecipient error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
+                return Ok(());
+            }
+            RuleAction::Delay(_delay) => {
+                extra_delay = _delay;
+            }
+            RuleAction::Corrupt => {
+                MutationMode::BitFlips(1).apply(&mut fragment, &mut self.rng);
             }
-            return;
         }
 
-        //sleeps
-        if !self.settings.sleep_duration.is_zero() {
-            thread::sleep(self.settings.sleep_duration);
+        //payload mutation, quack included
+        if let Some(mutation) = &self.settings.payload_mutation {
+            mutation.apply(&mut fragment, &mut self.rng);
         }
 
-        //all is good, propagate NACK normally
-        let mut p = packet.clone();
-        p.routing_header.hop_index += 1;
-        let res: Result<(), SendError<Packet>> = self.packet_send.get(&to).unwrap().send(p);
 
-        if res.is_err() {
-            panic!("{}", res.err().unwrap())
-        }
+        //all good, propagate packet as it should be
+        let fragment_index = fragment.fragment_index;
+        let mut header = packet.routing_header.clone();
+        header.hop_index += 1;
+        self.forward_disordered(to, Packet{
+            routing_header: header,
+            session_id: packet.session_id,
+            pack_type: (MsgFragment(fragment)),
+        }, extra_delay)?;
 
         //log it
-        if self.settings.log_to_stdout {
-            println!("RustableDrone {} received a NACK from node {} and forwarded it to node {}", self.id, from, to);
-        }
+        drone_log!(debug, self, from = from, to = to, packet_type = "MsgFragment", session_id = packet.session_id, fragment_index = fragment_index; "received a MsgFragment from node {} and forwarded it to node {}", from, to);
+        Ok(())
     }
 
-    fn ack_handler(&self, packet: &Packet, ack: Ack) {
-        let from: NodeId = packet.routing_header.hops[packet.routing_header.hop_index - 1];
+    fn nack_handler(&mut self, packet: &Packet, nack: Nack) -> Result<(), DroneError> {
+        let from: NodeId = packet.routing_header.previous_hop().unwrap();
 
-        //destination is drone
+        //unexpected recipient, the packet can't be dropped and is delivered by the controller to its intended recipient
+        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
+            drone_log!(warn, self, from = from, packet_type = "Nack", session_id = packet.session_id, fragment_index = nack.fragment_index, error = "UnexpectedRecipient"; "encountered an UnexpectedRecipient error while receiving a NACK from node {} and sent it through the controller", from);
+            return self.send_event(DroneEvent::ControllerShortcut(packet.clone()));
+        }
+
+        //destination is drone, the packet has nowhere to go and is dropped, reporting it to the controller
         if packet.routing_header.is_last_hop() {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered a DestinationIsDrone error while receiving an ACK from node {}", self.id, from);
-                }
-                self.send_nack_through_controller(packet, ack.fragment_index, DestinationIsDrone);
-            } else {
-                panic!("RustableDrone {} encountered a DestinationIsDrone error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-            }
-            return;
+            drone_log!(warn, self, from = from, packet_type = "Nack", session_id = packet.session_id, fragment_index = nack.fragment_index, error = "DestinationIsDrone"; "encountered a DestinationIsDrone error while receiving a NACK from node {} and dropped it", from);
+            return self.drop_undeliverable(from, packet);
         }
 
-        let to: NodeId = packet.routing_header.hops[packet.routing_header.hop_index + 1];
+        let to: NodeId = packet.routing_header.next_hop().unwrap();
 
-        //routing error
+        //routing error, the packet can't be dropped and is delivered by the controller
         if !self.packet_send.contains_key(&to) {
-            //sends routing error nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an ErrorInRouting while trying to forward an ACK from node {} to node {}", self.id, from, to);
-                }
-                self.send_nack_through_controller(packet, ack.fragment_index, ErrorInRouting(to));
-            } else {
-                panic!("RustableDrone {} encountered an ErrorInRouting while trying to forward an ACK to node {} but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, to, from);
-            }
-            return;
+            drone_log!(warn, self, from = from, to = to, packet_type = "Nack", session_id = packet.session_id, fragment_index = nack.fragment_index, error = "ErrorInRouting"; "encountered an ErrorInRouting while trying to forward a NACK from node {} to node {} and sent it through the controller", from, to);
+            return self.send_through_controller(packet);
         }
 
-        //unexpected recipient
-        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an UnexpectedRecipient error while trying to forward an ACK from node {} to node {}", self.id, from, to);
-                }
-                self.send_nack_through_controller(packet, ack.fragment_index, UnexpectedRecipient(self.id));
-            } else {
-                panic!("RustableDrone {} encountered an UnexpectedRecipient error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-            }
-            return;
-        }
 
-        //sleeps
-        if !self.settings.sleep_duration.is_zero() {
-            thread::sleep(self.settings.sleep_duration);
-        }
+        //filter, the packet can't be dropped and is delivered by the controller
+        let extra_delay = match self.filter_action(packet, from, Some(to)) {
+            RuleAction::Drop | RuleAction::DropWithNack => {
+                drone_log!(info, self, from = from, to = to, packet_type = "Nack", session_id = packet.session_id, fragment_index = nack.fragment_index; "filtered a NACK received from node {} directed to node {} and sent it through the controller", from, to);
+                return self.send_through_controller(packet);
+            }
+            RuleAction::Delay(_delay) => _delay,
+            //only fragments carry data that can be corrupted
+            RuleAction::Allow | RuleAction::Corrupt => Duration::ZERO
+        };
 
         //all is good, propagate NACK normally
         let mut p = packet.clone();
         p.routing_header.hop_index += 1;
-        let res: Result<(), SendError<Packet>> = self.packet_send.get(&to).unwrap().send(p);
-
-        if res.is_err() {
-            panic!("{}", res.err().unwrap())
+        if self.settings.disorder_acks_and_nacks {
+            self.forward_disordered(to, p, extra_delay)?;
+        } else {
+            self.forward_after(to, p, extra_delay)?;
         }
 
         //log it
-        if self.settings.log_to_stdout {
-            println!("RustableDrone {} received an ACK from node {} and forwarded it to node {}", self.id, from, to);
-        }
+        drone_log!(debug, self, from = from, to = to, packet_type = "Nack", session_id = packet.session_id, fragment_index = nack.fragment_index; "received a NACK from node {} and forwarded it to node {}", from, to);
+        Ok(())
     }
 
-    fn flood_req_handler(&mut self,  packet: &Packet, mut request: FloodRequest ) {
-        //checks
-        let from: NodeId = request.path_trace[request.path_trace.len() - 1 ].0;
+    fn ack_handler(&mut self, packet: &Packet, ack: Ack) -> Result<(), DroneError> {
+        let from: NodeId = packet.routing_header.hops[packet.routing_header.hop_index - 1];
 
-        request.path_trace.push((self.id, Drone));
+        //unexpected recipient, the packet can't be dropped and is delivered by the controller to its intended recipient
+        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
+            drone_log!(warn, self, from = from, packet_type = "Ack", session_id = packet.session_id, fragment_index = ack.fragment_index, error = "UnexpectedRecipient"; "encountered an UnexpectedRecipient error while receiving an ACK from node {} and sent it through the controller", from);
+            return self.send_event(DroneEvent::ControllerShortcut(packet.clone()));
+        }
 
-        //adds flood initiator if not present
-        if !self.flood_ids.contains_key(&request.initiator_id) {
-            self.flood_ids.insert(request.initiator_id, vec![]);
+        //destination is drone, the packet has nowhere to go and is dropped, reporting it to the controller
+        if packet.routing_header.is_last_hop() {
+            drone_log!(warn, self, from = from, packet_type = "Ack", session_id = packet.session_id, fragment_index = ack.fragment_index, error = "DestinationIsDrone"; "encountered a DestinationIsDrone error while receiving an ACK from node {} and dropped it", from);
+            return self.drop_undeliverable(from, packet);
         }
 
-        //check if flood ID has already been sent by this initiator
-        if self.flood_ids.get(&request.initiator_id).unwrap().contains(&request.flood_id) {
-            //send back a FloodResponse
-            let mut rev_route: Vec<NodeId> = vec![];
-            for i in (0..request.path_trace.len()).rev() {
-                rev_route.push(request.path_trace[i].0);
-            }
+        let to: NodeId = packet.routing_header.hops[packet.routing_header.hop_index + 1];
 
-            //sleeps
-            if !self.settings.sleep_duration.is_zero() {
-                thread::sleep(self.settings.sleep_duration);
-            }
+        //routing error, the packet can't be dropped and is delivered by the controller
+        if !self.packet_send.contains_key(&to) {
+            drone_log!(warn, self, from = from, to = to, packet_type = "Ack", session_id = packet.session_id, fragment_index = ack.fragment_index, error = "ErrorInRouting"; "encountered an ErrorInRouting while trying to forward an ACK from node {} to node {} and sent it through the controller", from, to);
+            return self.send_through_controller(packet);
+        }
 
-            let res: Result<(), SendError<Packet>> = self.packet_send.get(&from).unwrap().send(
-                Packet {
-                    pack_type: PacketType::FloodResponse(FloodResponse { flood_id: request.flood_id, path_trace: request.path_trace }),
-                    routing_header: SourceRoutingHeader{
-                        hop_index: 1,
-                        hops: rev_route,
-                    },
-                    session_id: packet.session_id,
-                }
-            );
 
-            if res.is_err() {
-                panic!("{}", res.err().unwrap())
+        //filter, the packet can't be dropped and is delivered by the controller
+        let extra_delay = match self.filter_action(packet, from, Some(to)) {
+            RuleAction::Drop | RuleAction::DropWithNack => {
+                drone_log!(info, self, from = from, to = to, packet_type = "Ack", session_id = packet.session_id, fragment_index = ack.fragment_index; "filtered an ACK received from node {} directed to node {} and sent it through the controller", from, to);
+                return self.send_through_controller(packet);
             }
+            RuleAction::Delay(_delay) => _delay,
+            //only fragments carry data that can be corrupted
+            RuleAction::Allow | RuleAction::Corrupt => Duration::ZERO
+        };
 
-            //log it
-            if self.settings.log_to_stdout {
-                println!("RustableDrone {} sent a FloodResponse to node {} because it was already visited", self.id, from);
-            }
+        //all is good, propagate ACK normally
+        let mut p = packet.clone();
+        p.routing_header.hop_index += 1;
+        if self.settings.disorder_acks_and_nacks {
+            self.forward_disordered(to, p, extra_delay)?;
         } else {
-            //adds flood ID to this initiator
-            self.flood_ids.get_mut(&request.initiator_id).unwrap().push(request.flood_id);
-
-            //if drone has no neighbors
-            if self.packet_send.len() == 1 {
-                //sends back a FloodResponse
-                let mut rev_route: Vec<NodeId> = vec![];
-                for i in (0..request.path_trace.len()).rev() {
-                    rev_route.push(request.path_trace[i].0);
-                }
+            self.forward_after(to, p, extra_delay)?;
+        }
 
-                //sleeps
-                if !self.settings.sleep_duration.is_zero() {
-                    thread::sleep(self.settings.sleep_duration);
-                }
+        //log it
+        drone_log!(debug, self, from = from, to = to, packet_type = "Ack", session_id = packet.session_id, fragment_index = ack.fragment_index; "received an ACK from node {} and forwarded it to node {}", from, to);
+        Ok(())
+    }
 
-                let res: Result<(), SendError<Packet>> = self.packet_send.get(&from).unwrap().send(
-                    Packet {
-                        pack_type: PacketType::FloodResponse(FloodResponse { flood_id: request.flood_id, path_trace: request.path_trace }),
-                        routing_header: SourceRoutingHeader{
-                            hop_index: 1,
-                            hops: rev_route,
-                        },
-                        session_id: packet.session_id,
-                    }
-                );
+    fn flood_req_handler(&mut self,  packet: &Packet, mut request: FloodRequest ) -> Result<(), DroneError> {
+        //the sender is the last node of the path trace, or the initiator if it left itself out
+        let from: NodeId = Self::flood_sender(&request);
+        if !request.path_trace.iter().any(|(id, _)| *id == request.initiator_id) {
+            //its type is unknown, flooding is usually started by clients
+            request.path_trace.insert(0, (request.initiator_id, Client));
+            drone_log!(warn, self, from = from, packet_type = "FloodRequest", session_id = packet.session_id, flood_id = request.flood_id, initiator = request.initiator_id; "received a FloodRequest without its initiator {} in the path trace and added it", request.initiator_id);
+        }
 
-                if res.is_err() {
-                    panic!("{}", res.err().unwrap())
-                }
+        //filter, the request is dropped
+        let extra_delay = match self.filter_action(packet, from, None) {
+            RuleAction::Drop | RuleAction::DropWithNack => {
+                drone_log!(info, self, from = from, packet_type = "FloodRequest", session_id = packet.session_id, flood_id = request.flood_id, initiator = request.initiator_id; "filtered a FloodRequest received from node {}", from);
+                return Ok(());
+            }
+            RuleAction::Delay(_delay) => _delay,
+            RuleAction::Allow | RuleAction::Corrupt => Duration::ZERO
+        };
 
-                //log it
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} sent a FloodResponse to node {} because it has no neighbors", self.id, from);
-                }
-            } else { //forward FloodRequest
-                //sleeps
-                if !self.settings.sleep_duration.is_zero() {
-                    thread::sleep(self.settings.sleep_duration);
-                }
+        request.path_trace.push((self.id, Drone));
 
-                //propagate request to all neighbors except the sender
-                for (key, value) in self.packet_send.iter().filter(|(k, _)| **k != from) {
-                    let res: Result<(), SendError<Packet>> = self.packet_send.get(&key).unwrap().send(
-                        Packet{
-                            routing_header: Default::default(),
-                            session_id: packet.session_id,
-                            pack_type: PacketType::FloodRequest(request.clone()),
-                        }
-                    );
+        //check if flood ID has already been sent by this initiator, remembering it otherwise
+        if !self.flood_ids.insert(request.initiator_id, request.flood_id) {
+            drone_log!(debug, self, from = from, packet_type = "FloodRequest", session_id = packet.session_id, flood_id = request.flood_id, initiator = request.initiator_id; "sent a FloodResponse to node {} because it was already visited", from);
+            return self.send_flood_response(from, packet.session_id, request, extra_delay);
+        }
 
-                    if res.is_err() {
-                        panic!("{}", res.err().unwrap())
-                    }
+        //if drone has no neighbors other than the sender
+        let mut neighbors: Vec<NodeId> = self.packet_send.keys().filter(|k| **k != from).copied().collect();
+        //the HashMap order changes between runs, and each forward draws from the rng
+        neighbors.sort_unstable();
+        if neighbors.is_empty() {
+            drone_log!(debug, self, from = from, packet_type = "FloodRequest", session_id = packet.session_id, flood_id = request.flood_id, initiator = request.initiator_id; "sent a FloodResponse to node {} because it has no neighbors", from);
+            return self.send_flood_response(from, packet.session_id, request, extra_delay);
+        }
 
-                    //log it
-                    if self.settings.log_to_stdout {
-                        println!("RustableDrone {} received a FloodRequest from node {} and forwarded it to node {}", self.id, from, key);
-                    }
-                }
-            }
+        //propagate request to all neighbors except the sender, along the path it took so far
+        for key in neighbors {
+            let mut hops: Vec<NodeId> = request.path_trace.iter().map(|(id, _)| *id).collect();
+            hops.push(key);
+            self.forward_after(key, Packet{
+                routing_header: SourceRoutingHeader {
+                    hop_index: hops.len() - 1,
+                    hops,
+                },
+                session_id: packet.session_id,
+                pack_type: PacketType::FloodRequest(request.clone()),
+            }, extra_delay)?;
+
+            //log it
+            drone_log!(debug, self, from = from, to = key, packet_type = "FloodRequest", session_id = packet.session_id, flood_id = request.flood_id, initiator = request.initiator_id; "received a FloodRequest from node {} and forwarded it to node {}", from, key);
         }
+        Ok(())
     }
 
-    fn flood_res_handler(&mut self, packet: &Packet) {
+    fn flood_res_handler(&mut self, packet: &Packet) -> Result<(), DroneError> {
         let from: NodeId = packet.routing_header.hops[packet.routing_header.hop_index - 1];
 
-        //destination is drone
-        if packet.routing_header.hop_index == packet.routing_header.hops.len() {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered a DestinationIsDrone error while receiving a NACK from node {}", self.id, from);
-                }
-                self.send_nack_through_controller(packet, u64::MAX, DestinationIsDrone);
-            } else {
-                panic!("RustableDrone {} is the destination of the packet but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-            }
-            return;
+        //unexpected recipient, the packet can't be dropped and is delivered by the controller to its intended recipient
+        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
+            drone_log!(warn, self, from = from, packet_type = "FloodResponse", session_id = packet.session_id, error = "UnexpectedRecipient"; "encountered an UnexpectedRecipient error while receiving a FloodResponse from node {} and sent it through the controller", from);
+            return self.send_event(DroneEvent::ControllerShortcut(packet.clone()));
         }
 
-        let to: NodeId = packet.routing_header.hops[packet.routing_header.hop_index + 1];
-        //let to: NodeId = packet.routing_header.next_hop().unwrap();
+        //destination is drone, the packet has nowhere to go and is dropped, reporting it to the controller
+        if packet.routing_header.is_last_hop() {
+            drone_log!(warn, self, from = from, packet_type = "FloodResponse", session_id = packet.session_id, error = "DestinationIsDrone"; "encountered a DestinationIsDrone error while receiving a FloodResponse from node {} and dropped it", from);
+            return self.drop_undeliverable(from, packet);
+        }
 
+        let to: NodeId = packet.routing_header.hops[packet.routing_header.hop_index + 1];
 
-        //routing error
+        //routing error, the packet can't be dropped and is delivered by the controller
         if !self.packet_send.contains_key(&to) {
-            //sends routing error nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an ErrorInRouting while trying to forward a NACK from node {} to node {}", self.id, from, to);
-                }
-                self.send_nack_through_controller(packet, u64::MAX, ErrorInRouting(to));
-            } else {
-                panic!("RustableDrone {} encountered an ErrorInRouting (to node {}) but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, to, from);
-            }
-            return;
-        }
-
-        //unexpected recipient
-        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
-            //sends destination is drone nack
-            if self.packet_send.contains_key(&from) {
-                if self.settings.log_to_stdout {
-                    println!("RustableDrone {} encountered an UnexpectedRecipient error while trying to forward a NACK from node {} to node {}", self.id, from, to);
-                }
-                self.send_nack_through_controller(packet, u64::MAX, UnexpectedRecipient(self.id));
-            } else {
-                panic!("RustableDrone {} encountered an UnexpectedRecipient error but was incapable of sending a NACK back to node {}, as the channel does not exist", self.id, from);
-            }
-            return;
+            drone_log!(warn, self, from = from, to = to, packet_type = "FloodResponse", session_id = packet.session_id, error = "ErrorInRouting"; "encountered an ErrorInRouting while trying to forward a FloodResponse from node {} to node {} and sent it through the controller", from, to);
+            return self.send_through_controller(packet);
         }
 
 
-        //sleeps
-        if !self.settings.sleep_duration.is_zero() {
-            thread::sleep(self.settings.sleep_duration);
-        }
+        //filter, the packet can't be dropped and is delivered by the controller
+        let extra_delay = match self.filter_action(packet, from, Some(to)) {
+            RuleAction::Drop | RuleAction::DropWithNack => {
+                drone_log!(info, self, from = from, to = to, packet_type = "FloodResponse", session_id = packet.session_id; "filtered a FloodResponse received from node {} directed to node {} and sent it through the controller", from, to);
+                return self.send_through_controller(packet);
+            }
+            RuleAction::Delay(_delay) => _delay,
+            //only fragments carry data that can be corrupted
+            RuleAction::Allow | RuleAction::Corrupt => Duration::ZERO
+        };
 
         //allgood
         let mut p = packet.clone();
         p.routing_header.hop_index += 1;
-        let res: Result<(), SendError<Packet>> = self.packet_send.get(&to).unwrap().send(p);
-
-        if res.is_err() {
-            panic!("{}", res.err().unwrap())
-        }
+        self.forward_after(to, p, extra_delay)?;
 
         //log it
-        if self.settings.log_to_stdout {
-            println!("RustableDrone {} received a FloodResponse from node {} and forwarded it to node {}", self.id, from, to);
-        }
+        drone_log!(debug, self, from = from, to = to, packet_type = "FloodResponse", session_id = packet.session_id; "received a FloodResponse from node {} and forwarded it to node {}", from, to);
+        Ok(())
     }
 
     /// Handles a DroneCommand
     fn command_handler(&mut self, command: Result<DroneCommand, RecvError>) {
-        if command.is_err() {
-            panic!("Command Error in drone: {}\n{}", self.id, command.err().unwrap());
+        match command {
+            Ok(command) => self.execute_command(RustableCommand::from(command)),
+            Err(_) => {
+                self.handle_error(DroneError::CommandChannelDisconnected, None);
+                //stop listening to the controller, packets are still handled
+                self.controller_recv = never();
+            }
+        }
+    }
+
+    /// Handles a DroneCommand received while crashing, where only RemoveSender is processed
+    fn crashing_command_handler(&mut self, command: Result<DroneCommand, RecvError>) {
+        match command {
+            Ok(DroneCommand::RemoveSender(_id)) => {
+                self.execute_command(RustableCommand::DroneCommand(DroneCommand::RemoveSender(_id)));
+            }
+            Ok(_) => {}
+            Err(_) => {
+                //the controller is gone, keep waiting for the neighbors to disconnect
+                self.controller_recv = never();
+            }
         }
+    }
 
-        match RustableCommand::from(command.unwrap()) {
+    /// Handles a RustableCommand while crashing: only statistics are still served, and senders removed
+    fn crashing_rustable_command_handler(&mut self, command: Result<RustableCommand, RecvError>) {
+        match command {
+            Ok(command @ (RustableCommand::QueryStats(_) | RustableCommand::DroneCommand(DroneCommand::RemoveSender(_)))) => {
+                self.execute_command(command);
+            }
+            Ok(_) => {}
+            Err(_) => {
+                self.rustable_recv = never();
+            }
+        }
+    }
+
+    /// Handles a RustableCommand
+    fn rustable_command_handler(&mut self, command: Result<RustableCommand, RecvError>) {
+        match command {
+            Ok(command) => self.execute_command(command),
+            Err(_) => {
+                //the extension channel is optional, stop listening to it once every sender is gone
+                self.rustable_recv = never();
+            }
+        }
+    }
+
+    /// Executes a RustableCommand
+    fn execute_command(&mut self, command: RustableCommand) {
+        match command {
             RustableCommand::DroneCommand(command) => {
                 match command {
                     DroneCommand::AddSender(_id, _sender) => {
-                        let id = self.id;
-                        let res = self.add_channel(_id, _sender);
-                        if res.is_err() {
-                            eprintln!("DroneCommand AddSender failed in RustableDrone {}: {}", id, res.err().unwrap())
+                        if let Err(error) = self.add_channel(_id, _sender) {
+                            drone_error!(self, to = _id, error = error; "failed the DroneCommand AddSender: {}", error);
                         }
                     }
                     DroneCommand::RemoveSender(_id) => {
-                        let id = self.id;
-                        let res = self.remove_channel(_id);
-                        if res.is_err() {
-                            eprintln!("DroneCommand RemoveSender failed in RustableDrone {}: {}", id, res.err().unwrap())
+                        if let Err(error) = self.remove_channel(_id) {
+                            drone_error!(self, to = _id, error = error; "failed the DroneCommand RemoveSender: {}", error);
                         }
                     }
                     DroneCommand::SetPacketDropRate(_pdr) => {
-                        if _pdr < 0.0f32 || _pdr > 1.0f32 {
-                            eprintln!("Invalid packet drop rate value")
+                        if !(0.0f32..=1.0f32).contains(&_pdr) {
+                            drone_error!(self, pdr = _pdr; "received an invalid packet drop rate value: {}", _pdr);
                         } else {
                             self.drop_rate = _pdr;
                         }
                     }
                     DroneCommand::Crash => {
-                        self.has_to_crash = true;
+                        self.state = DroneState::Crashing;
+                        //a crashing drone no longer follows its schedule
+                        self.faults = DelayQueue::default();
                     }
                 }
             }
@@ -573,7 +788,7 @@ impl RustableDrone {
                         self.settings.log_to_stdout = _val;
                     }
                     SettingsCommand::SleepDuration(_duration) => {
-                        self.settings.sleep_duration = _duration.clone();
+                        self.settings.latency = LatencyDistribution::Fixed(_duration);
                     }
                     SettingsCommand::AwaitQueuedPacketsOnCrash(_val) => {
                         self.settings.await_queued_packets_on_crash = _val;
@@ -584,6 +799,62 @@ impl RustableDrone {
                     SettingsCommand::SendNackOnFilteredPackets(_val) => {
                         self.settings.send_nack_on_filtered_packet = _val;
                     }
+                    SettingsCommand::ErrorPolicy(_policy) => {
+                        self.settings.error_policy = _policy;
+                    }
+                    SettingsCommand::FloodCacheCapacity(_capacity) => {
+                        self.settings.flood_cache_capacity = _capacity;
+                        self.flood_ids.set_capacity(_capacity);
+                    }
+                    SettingsCommand::FloodCacheTtl(_ttl) => {
+                        self.settings.flood_cache_ttl = _ttl;
+                        self.flood_ids.set_ttl(_ttl);
+                    }
+                    SettingsCommand::DropRateLink(_link) => {
+                        self.settings.drop_rate_link = _link;
+                    }
+                    SettingsCommand::LossModel(_model) => {
+                        if let Err(error) = self.set_loss_model_type(_model) {
+                            drone_error!(self, error = error.to_string(); "received an invalid loss model: {}", error);
+                        }
+                    }
+                    SettingsCommand::Seed(_seed) => {
+                        self.settings.seed = Some(_seed);
+                        self.rng = StdRng::seed_from_u64(_seed);
+                    }
+                    SettingsCommand::PayloadMutation(_mutation) => {
+                        self.settings.payload_mutation = _mutation;
+                    }
+                    SettingsCommand::DuplicateProbability(_probability) => {
+                        self.settings.duplicate_probability = _probability;
+                    }
+                    SettingsCommand::Reorder(_reorder) => {
+                        self.settings.reorder = _reorder;
+                    }
+                    SettingsCommand::DisorderAcksAndNacks(_val) => {
+                        self.settings.disorder_acks_and_nacks = _val;
+                    }
+                    SettingsCommand::Latency(_latency) => {
+                        self.settings.latency = _latency;
+                    }
+                    SettingsCommand::LinkLatency(_id, _latency) => {
+                        match _latency {
+                            Some(_latency) => self.settings.link_latencies.insert(_id, _latency),
+                            None => self.settings.link_latencies.remove(&_id)
+                        };
+                    }
+                    SettingsCommand::RateLimit(_limit) => {
+                        self.settings.rate_limit = _limit;
+                    }
+                    SettingsCommand::LinkRateLimit(_id, _limit) => {
+                        match _limit {
+                            Some(_limit) => self.settings.link_rate_limits.insert(_id, _limit),
+                            None => self.settings.link_rate_limits.remove(&_id)
+                        };
+                    }
+                    SettingsCommand::Scheduling(_policy) => {
+                        self.settings.scheduling = _policy;
+                    }
                 }
             }
             RustableCommand::FilterCommand(command) => {
@@ -603,16 +874,66 @@ impl RustableDrone {
                     FilterCommand::SetType(_type) => {
                         self.filter.set_type(_type);
                     }
+                    FilterCommand::AddIdWithKinds(_id, _kinds) => {
+                        self.filter.add_with_kinds(_id, _kinds);
+                    }
+                    FilterCommand::SetKinds(_kinds) => {
+                        self.filter.set_kinds(_kinds);
+                    }
+                    FilterCommand::AddRule(_rule) => {
+                        self.filter.add_rule(_rule);
+                    }
+                    FilterCommand::InsertRule(_index, _rule) => {
+                        self.filter.insert_rule(_index, _rule);
+                    }
+                    FilterCommand::RemoveRule(_id) => {
+                        self.filter.remove_rule(_id);
+                    }
+                    FilterCommand::ClearRules => {
+                        self.filter.clear_rules();
+                    }
                 }
             }
             RustableCommand::Quack => {
-                self.settings.quack = !self.settings.quack;
+                let quacking = self.settings.payload_mutation.as_ref().is_some_and(PayloadMutation::is_quack);
+                self.settings.payload_mutation = if quacking { None } else { Some(PayloadMutation::quack()) };
+            }
+            RustableCommand::SetLinkDropRate(_id, _pdr) => {
+                if !(0.0f32..=1.0f32).contains(&_pdr) {
+                    drone_error!(self, to = _id, pdr = _pdr; "received an invalid packet drop rate value: {}", _pdr);
+                } else {
+                    self.settings.link_drop_rates.insert(_id, _pdr);
+                }
+            }
+            RustableCommand::ResetLinkDropRate(_id) => {
+                self.settings.link_drop_rates.remove(&_id);
+            }
+            RustableCommand::SetLossModel(_model) => {
+                self.loss_model = _model;
+                self.loss_model_type = None;
+            }
+            RustableCommand::SetSchedule(_schedule) => {
+                self.start_schedule(_schedule);
+            }
+            RustableCommand::QueryStats(_reply) => {
+                if _reply.send(self.stats.snapshot()).is_err() {
+                    drone_error!(self; "failed the RustableCommand QueryStats: the reply channel is disconnected");
+                }
             }
         }
     }
 
+    /// Returns the packet drop rate of a fragment going from one adjacent node to another
+    fn link_drop_rate(&self, from: NodeId, to: NodeId) -> f32 {
+        let link = match self.settings.drop_rate_link {
+            DropRateLink::Outgoing => to,
+            DropRateLink::Incoming => from
+        };
+        *self.settings.link_drop_rates.get(&link).unwrap_or(&self.drop_rate)
+    }
+
     /// Adds a channel to the list of adjacent nodes' channels
-    fn add_channel(&mut self, id: NodeId, sender: Sender<Packet>) -> Result<&str, &str> {
+    fn add_channel(&mut self, id: NodeId, sender: Sender<Packet>) -> Result<&'static str, &'static str> {
         if self.packet_send.contains_key(&id) {
             return Err("Channel to this NodeId already exists")
         }
@@ -622,7 +943,7 @@ impl RustableDrone {
     }
 
     /// Removes the channel to the specified adjacent node
-    fn remove_channel(&mut self, id: NodeId) -> Result<&str, &str> {
+    fn remove_channel(&mut self, id: NodeId) -> Result<&'static str, &'static str> {
         if ! self.packet_send.contains_key(&id) {
             return Err("No adjacent node with specified NodeId")
         }
@@ -634,53 +955,309 @@ impl RustableDrone {
     }
 
     /// Sends a nack with specified type back to where the packet came from
-    fn send_nack(&self, from: NodeId, nacked_packet: &Packet, fragment_index: u64, nack_type: NackType) {
-        //sleeps
-        if !self.settings.sleep_duration.is_zero() {
-            thread::sleep(self.settings.sleep_duration);
+    fn send_nack(&mut self, from: NodeId, nacked_packet: &Packet, fragment_index: u64, nack_type: NackType) -> Result<(), DroneError> {
+        let nack = self.create_nack(nacked_packet, fragment_index, nack_type);
+        self.forward(from, nack)
+    }
+
+    /// Sends a nack with specified type back to where the packet came from, through the controller if that node can't be reached anymore
+    fn send_nack_or_shortcut(&mut self, from: NodeId, nacked_packet: &Packet, fragment_index: u64, nack_type: NackType) -> Result<(), DroneError> {
+        if self.packet_send.contains_key(&from) {
+            return self.send_nack(from, nacked_packet, fragment_index, nack_type);
         }
 
+        //the nack can't be dropped and is delivered by the controller
+        drone_log!(warn, self, to = from, packet_type = "Nack", session_id = nacked_packet.session_id, fragment_index = fragment_index, error = "ErrorInRouting"; "could not send a NACK back to node {} and sent it through the controller", from);
+        let nack = self.create_nack(nacked_packet, fragment_index, nack_type);
+        self.send_event(DroneEvent::ControllerShortcut(nack))
+    }
+
+    /// Creates a nack with specified type for a packet, whose route goes back to where the packet came from
+    fn create_nack(&self, nacked_packet: &Packet, fragment_index: u64, nack_type: NackType) -> Packet {
+        self.stats.update(|stats| stats.nack(&nack_type));
+
+        //the path back from this drone, which is not in the header of a packet sent to the wrong recipient
         let mut rev_header = nacked_packet.routing_header.clone();
         rev_header.hops.truncate(rev_header.hop_index + 1);
         rev_header.hops.reverse();
+        rev_header.hops[0] = self.id;
         rev_header.hop_index = 1;
 
-        let res: Result<(), SendError<Packet>> = self.packet_send.get(&from).unwrap().send(
-            Packet {
-                pack_type: PacketType::Nack(Nack{
-                    fragment_index,
-                    nack_type,
-                }),
-                routing_header: rev_header,
-                session_id: nacked_packet.session_id,
+        Packet {
+            pack_type: PacketType::Nack(Nack{
+                fragment_index,
+                nack_type,
+            }),
+            routing_header: rev_header,
+            session_id: nacked_packet.session_id,
+        }
+    }
+
+    /// Returns what the filter decides for a packet coming from a node and going to another (None for FloodRequests).
+    /// The first matching rule decides, otherwise packets not allowed by the list are dropped.
+    /// Dropped packets get a nack if send_nack_on_filtered_packet is set, or if the rule says so
+    fn filter_action(&self, packet: &Packet, from: NodeId, to: Option<NodeId>) -> RuleAction {
+        if !self.settings.filter_packets {
+            return RuleAction::Allow;
+        }
+
+        let action = match self.filter.rule_action(packet, from, to) {
+            Some(RuleAction::Drop) if self.settings.send_nack_on_filtered_packet => RuleAction::DropWithNack,
+            Some(action) => action,
+            None if self.filter.is_allowed(from, PacketKind::of(&packet.pack_type)) => RuleAction::Allow,
+            None if self.settings.send_nack_on_filtered_packet => RuleAction::DropWithNack,
+            None => RuleAction::Drop
+        };
+
+        if matches!(action, RuleAction::Drop | RuleAction::DropWithNack) {
+            self.stats.update(|stats| stats.filtered(PacketKind::of(&packet.pack_type), from));
+            self.capture(Direction::Incoming, Some(from), Decision::Filtered, packet);
+        }
+        action
+    }
+
+    /// Sends a packet to its destination through the controller, as if it was forwarded to the next hop
+    fn send_through_controller(&self, packet: &Packet) -> Result<(), DroneError> {
+        let mut p = packet.clone();
+        p.routing_header.hop_index += 1;
+        self.send_event(DroneEvent::ControllerShortcut(p))
+    }
+
+    /// Drops an ack, nack or flood response whose destination is this drone, which the controller could only deliver back to it
+    fn drop_undeliverable(&self, from: NodeId, packet: &Packet) -> Result<(), DroneError> {
+        self.stats.update(|stats| stats.dropped(PacketKind::of(&packet.pack_type), Some(from)));
+        self.capture(Direction::Incoming, Some(from), Decision::Dropped, packet);
+        self.send_event(DroneEvent::PacketDropped(packet.clone()))
+    }
+
+    /// The node a FloodRequest comes from: the last node of its path trace, or its initiator when the path trace is empty
+    fn flood_sender(request: &FloodRequest) -> NodeId {
+        request.path_trace.last().map_or(request.initiator_id, |(id, _)| *id)
+    }
+
+    /// Sends a FloodResponse back along the path trace of a request, through the controller if the sender can't be reached
+    fn send_flood_response(&mut self, to: NodeId, session_id: u64, request: FloodRequest, extra_delay: Duration) -> Result<(), DroneError> {
+        let response = Packet {
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: request.path_trace.iter().rev().map(|(id, _)| *id).collect(),
+            },
+            session_id,
+            pack_type: PacketType::FloodResponse(FloodResponse { flood_id: request.flood_id, path_trace: request.path_trace }),
+        };
+
+        //the response can't be dropped and is delivered by the controller
+        if !self.packet_send.contains_key(&to) {
+            drone_log!(warn, self, to = to, packet_type = "FloodResponse", session_id = session_id, error = "ErrorInRouting"; "could not send a FloodResponse back to node {} and sent it through the controller", to);
+            return self.send_event(DroneEvent::ControllerShortcut(response));
+        }
+        self.forward_after(to, response, extra_delay)
+    }
+
+    /// Sends a packet to an adjacent node once its latency has passed, without blocking the drone
+    fn forward(&mut self, to: NodeId, packet: Packet) -> Result<(), DroneError> {
+        self.forward_after(to, packet, Duration::ZERO)
+    }
+
+    /// Sends a packet to an adjacent node once its latency plus an extra delay has passed, without blocking the drone
+    fn forward_after(&mut self, to: NodeId, packet: Packet, extra_delay: Duration) -> Result<(), DroneError> {
+        if !self.packet_send.contains_key(&to) {
+            return Err(DroneError::MissingChannel(to));
+        }
+
+        //the link's own distribution wins over the drone's one, extra delays are capped like latencies
+        let latency = self.settings.link_latencies.get(&to).unwrap_or(&self.settings.latency);
+        let delay = latency.sample(&mut self.rng).saturating_add(extra_delay).min(MAX_LATENCY);
+        if delay.is_zero() {
+            return self.transmit(to, packet);
+        }
+
+        let now = Instant::now();
+        self.delayed.push(now.checked_add(delay).unwrap_or(now + MAX_LATENCY), (to, packet));
+        Ok(())
+    }
+
+    /// Forwards a packet, possibly twice, holding every copy back by a random delay according to the settings
+    fn forward_disordered(&mut self, to: NodeId, packet: Packet, extra_delay: Duration) -> Result<(), DroneError> {
+        //random values are only drawn when the feature is enabled, keeping seeded runs unchanged otherwise
+        let copies = if self.settings.duplicate_probability > 0.0 && self.rng.gen::<f32>() < self.settings.duplicate_probability {
+            2
+        } else {
+            1
+        };
+
+        for _ in 0..copies {
+            let mut delay = extra_delay;
+            if let Some(reorder) = self.settings.reorder {
+                if self.rng.gen::<f32>() < reorder.probability {
+                    delay = delay.saturating_add(self.rng.gen_range(Duration::ZERO..=reorder.max_hold));
+                }
             }
-        );
+            self.forward_after(to, packet.clone(), delay)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a channel that fires when the earliest delayed packet, rate limited packet or scheduled fault is due
+    fn timer(&self) -> Receiver<Instant> {
+        let settings = &self.settings;
+        let rate_limited = self.rate_limiter.next_deadline(|id| Self::rate_limit(settings, id), Instant::now());
+        match self.delayed.next_deadline().into_iter().chain(self.faults.next_deadline()).chain(rate_limited).min() {
+            Some(deadline) => at(deadline),
+            None => never()
+        }
+    }
+
+    /// Sends every delayed packet whose deadline has passed
+    fn release_delayed_packets(&mut self) {
+        while let Some((to, packet)) = self.delayed.pop_due(Instant::now()) {
+            self.release(to, packet, true);
+        }
+    }
 
-        if res.is_err() {
-            panic!("{}", res.err().unwrap())
+    /// Sends every rate limited packet whose link has a token for it
+    fn release_rate_limited_packets(&mut self) {
+        loop {
+            let settings = &self.settings;
+            let Some((to, packet)) = self.rate_limiter.pop_ready(|id| Self::rate_limit(settings, id), Instant::now()) else { break };
+            self.release(to, packet, false);
+        }
+    }
+
+    /// Sends every rate limited and delayed packet right away, regardless of tokens and deadlines
+    fn flush_delayed_packets(&mut self) {
+        //rate limited packets were delayed first
+        while let Some((to, packet)) = self.rate_limiter.pop() {
+            self.release(to, packet, false);
+        }
+        while let Some((to, packet)) = self.delayed.pop() {
+            self.release(to, packet, false);
+        }
+    }
+
+    /// Sends a held packet, through the link's rate limiter if specified, applying the error policy if something goes wrong.
+    /// The channel might have been removed while the packet was held, which makes it a routing error
+    fn release(&mut self, to: NodeId, packet: Packet, rate_limited: bool) {
+        let res = if !self.packet_send.contains_key(&to) {
+            self.unroutable(to, packet.clone())
+        } else if rate_limited {
+            self.transmit(to, packet.clone())
+        } else {
+            self.send_packet(to, packet.clone())
+        };
+
+        if let Err(error) = res {
+            self.handle_error(error, Some(&packet));
+        }
+    }
+
+    /// Handles a held packet whose adjacent node was removed while it was held
+    fn unroutable(&mut self, to: NodeId, packet: Packet) -> Result<(), DroneError> {
+        //log it
+        drone_log!(warn, self, to = to, packet_type = debug_field(PacketKind::of(&packet.pack_type)), session_id = packet.session_id, error = "ErrorInRouting"; "could not send a held packet to node {}, as it is no longer an adjacent node", to);
+
+        match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                //the fragment was already forwarded, it is nacked as if it was just received
+                let mut unrouted = packet.clone();
+                unrouted.routing_header.hop_index = unrouted.routing_header.hop_index.saturating_sub(1);
+                match unrouted.routing_header.previous_hop() {
+                    Some(from) => self.send_nack_or_shortcut(from, &unrouted, fragment.fragment_index, ErrorInRouting(to)),
+                    None => Ok(())
+                }
+            }
+            //flood requests can be lost
+            PacketType::FloodRequest(_) => Ok(()),
+            //the other packets can't be dropped and are delivered by the controller
+            _ => self.send_event(DroneEvent::ControllerShortcut(packet))
         }
     }
 
-    /// Sends a nack with specified type back to where the packet came from, through the controller
-    fn send_nack_through_controller(&self, nacked_packet: &Packet, fragment_index: u64, nack_type: NackType) {
-        //sleeps
-        if !self.settings.sleep_duration.is_zero() {
-            thread::sleep(self.settings.sleep_duration);
+    /// Returns the rate limit of the link to an adjacent node
+    fn rate_limit(settings: &DroneSettings, to: NodeId) -> Option<RateLimit> {
+        settings.link_rate_limits.get(&to).or(settings.rate_limit.as_ref()).copied()
+    }
+
+    /// Sends a packet to an adjacent node through the link's rate limiter, which might queue or reject it
+    fn transmit(&mut self, to: NodeId, packet: Packet) -> Result<(), DroneError> {
+        let limit = Self::rate_limit(&self.settings, to);
+        match self.rate_limiter.admit(to, limit.as_ref(), packet, Instant::now()) {
+            Admission::Send(packet) => self.send_packet(to, packet),
+            Admission::Queued => Ok(()),
+            Admission::Rejected(packet) => self.tail_drop(to, packet)
         }
+    }
 
-        let res: Result<(), SendError<DroneEvent>> = self.controller_send.send(DroneEvent::ControllerShortcut(
-            Packet {
-                pack_type: PacketType::Nack(Nack{
-                    fragment_index,
-                    nack_type
-                }),
-                routing_header: nacked_packet.routing_header.get_reversed(),
-                session_id: nacked_packet.session_id
+    /// Handles a packet that did not fit in the queue of a rate limited link
+    fn tail_drop(&mut self, to: NodeId, packet: Packet) -> Result<(), DroneError> {
+        //log it
+        drone_log!(info, self, to = to, packet_type = debug_field(PacketKind::of(&packet.pack_type)), session_id = packet.session_id; "tail-dropped a packet directed to node {}, as the link is congested", to);
+        self.stats.update(|stats| stats.dropped(PacketKind::of(&packet.pack_type), Some(to)));
+        self.capture(Direction::Outgoing, Some(to), Decision::Dropped, &packet);
+
+        match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                self.send_event(DroneEvent::PacketDropped(packet.clone()))?;
+
+                //the fragment was already forwarded, it is nacked as if it was just received
+                let mut dropped = packet.clone();
+                dropped.routing_header.hop_index = dropped.routing_header.hop_index.saturating_sub(1);
+                match dropped.routing_header.previous_hop() {
+                    Some(from) => self.send_nack_or_shortcut(from, &dropped, fragment.fragment_index, Dropped),
+                    None => Ok(())
+                }
             }
-        ));
+            //flood requests can be lost
+            PacketType::FloodRequest(_) => Ok(()),
+            //the other packets can't be dropped and are delivered by the controller
+            _ => self.send_event(DroneEvent::ControllerShortcut(packet))
+        }
+    }
+
+    /// Sends a packet to an adjacent node and notifies the controller
+    fn send_packet(&self, to: NodeId, packet: Packet) -> Result<(), DroneError> {
+        let sender = self.packet_send.get(&to).ok_or(DroneError::MissingChannel(to))?;
+        sender.send(packet.clone()).map_err(|_| DroneError::PacketSendFailed(to))?;
+
+        let payload_bytes = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => fragment.length as u64,
+            _ => 0
+        };
+        self.stats.update(|stats| stats.sent(PacketKind::of(&packet.pack_type), to, payload_bytes));
+        self.capture(Direction::Outgoing, Some(to), Decision::Sent, &packet);
+
+        self.send_event(DroneEvent::PacketSent(packet))
+    }
 
-        if res.is_err() {
-            panic!("{}", res.err().unwrap())
+    /// Sends an event to the controller
+    fn send_event(&self, event: DroneEvent) -> Result<(), DroneError> {
+        if let DroneEvent::ControllerShortcut(packet) = &event {
+            self.stats.update(|stats| stats.shortcut(PacketKind::of(&packet.pack_type)));
+            self.capture(Direction::Outgoing, None, Decision::Shortcut, packet);
+        }
+
+        //the sink is optional, a disconnected one is ignored
+        if let Some(sink) = &self.event_sink {
+            let _ = sink.send(event.clone());
+        }
+        self.controller_send.send(event).map_err(|_| DroneError::EventSendFailed)
+    }
+
+    /// Reacts to an error according to the error policy
+    fn handle_error(&self, error: DroneError, packet: Option<&Packet>) {
+        match self.settings.error_policy {
+            ErrorPolicy::Panic => {
+                panic!("RustableDrone {} encountered an error: {}", self.id, error);
+            }
+            ErrorPolicy::LogAndDrop => {
+                drone_error!(self, error = error.to_string(); "encountered an error: {}", error);
+            }
+            ErrorPolicy::ReportToController => {
+                let reported = packet.is_some_and(|p| self.send_event(DroneEvent::PacketDropped(p.clone())).is_ok());
+                if !reported {
+                    drone_error!(self, error = error.to_string(); "encountered an error: {}", error);
+                }
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use crossbeam_channel::{Receiver, Sender};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::network::NodeId;
+use wg_2024::packet::Packet;
+use crate::capture::CaptureSink;
+use crate::controller_commands::{RustableCommand, SettingsCommand};
+use crate::drone::RustableDrone;
+use crate::drone_error::DroneError;
+use crate::drone_settings::DroneSettings;
+use crate::fault_schedule::{FaultAction, FaultSchedule};
+use crate::loss_model::LossModel;
+use crate::packets_filter::PacketFilter;
+
+/// Builds a [RustableDrone] with its initial settings, filter and optional extra channels.
+/// Unlike [wg_2024::drone::Drone::new], invalid values are returned as a [DroneError] instead of panicking.
+///
+/// ```ignore
+/// let drone = RustableDroneBuilder::new(id, controller_send, controller_recv, packet_recv, packet_send)
+///     .pdr(0.1)
+///     .settings(settings)
+///     .seed(42)
+///     .command_channel(rustable_recv)
+///     .build()?;
+/// ```
+pub struct RustableDroneBuilder {
+    pub(crate) id: NodeId,
+    pub(crate) controller_send: Sender<DroneEvent>,
+    pub(crate) controller_recv: Receiver<DroneCommand>,
+    pub(crate) packet_recv: Receiver<Packet>,
+    pub(crate) packet_send: HashMap<NodeId, Sender<Packet>>,
+    pub(crate) pdr: f32,
+    pub(crate) settings: DroneSettings,
+    pub(crate) filter: PacketFilter,
+    pub(crate) seed: Option<u64>,
+    pub(crate) command_channel: Option<Receiver<RustableCommand>>,
+    pub(crate) event_sink: Option<Sender<DroneEvent>>,
+    pub(crate) capture: Option<CaptureSink>,
+    pub(crate) loss_model: Option<Box<dyn LossModel>>,
+    pub(crate) schedule: FaultSchedule
+}
+
+impl RustableDroneBuilder {
+    /// creates a builder with the channels every drone needs, a pdr of 0.0, default settings and default filter
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>
+    ) -> Self {
+        RustableDroneBuilder {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            pdr: 0.0,
+            settings: DroneSettings::default(),
+            filter: PacketFilter::default(),
+            seed: None,
+            command_channel: None,
+            event_sink: None,
+            capture: None,
+            loss_model: None,
+            schedule: FaultSchedule::default()
+        }
+    }
+
+    /// sets the packet drop rate
+    pub fn pdr(mut self, pdr: f32) -> Self {
+        self.pdr = pdr;
+        self
+    }
+
+    /// sets the initial settings
+    pub fn settings(mut self, settings: DroneSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// sets the initial filter
+    pub fn filter(mut self, filter: PacketFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// sets the initial settings, filter and fault schedule from a profile read from a configuration file
+    #[cfg(feature = "serde")]
+    pub fn profile(self, profile: crate::config::DroneProfile) -> Self {
+        self.settings(profile.settings).filter(profile.filter).schedule(profile.schedule)
+    }
+
+    /// sets the seed of the random number generator, overriding the one in the settings
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// sets the channel the drone receives [RustableCommand]s from
+    pub fn command_channel(mut self, command_channel: Receiver<RustableCommand>) -> Self {
+        self.command_channel = Some(command_channel);
+        self
+    }
+
+    /// sets a channel that receives a copy of every event sent to the controller
+    pub fn event_sink(mut self, event_sink: Sender<DroneEvent>) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// sets a sink that records every packet received, sent, dropped or filtered by the drone
+    pub fn capture(mut self, capture: CaptureSink) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// sets a custom loss model, used instead of the one selected in the settings
+    pub fn loss_model(mut self, loss_model: Box<dyn LossModel>) -> Self {
+        self.loss_model = Some(loss_model);
+        self
+    }
+
+    /// sets the fault schedule, started when the drone starts running
+    pub fn schedule(mut self, schedule: FaultSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// checks the values and builds the drone
+    pub fn build(mut self) -> Result<RustableDrone, DroneError> {
+        if !(0.0f32..=1.0f32).contains(&self.pdr) {
+            return Err(DroneError::InvalidDropRate(self.pdr));
+        }
+        if let Some(pdr) = self.settings.link_drop_rates.values().find(|pdr| !(0.0f32..=1.0f32).contains(*pdr)) {
+            return Err(DroneError::InvalidDropRate(*pdr));
+        }
+        for fault in self.schedule.faults() {
+            match &fault.action {
+                FaultAction::SetPacketDropRate(pdr) | FaultAction::SetLinkDropRate(_, pdr) if !(0.0f32..=1.0f32).contains(pdr) => {
+                    return Err(DroneError::InvalidDropRate(*pdr));
+                }
+                FaultAction::Setting(SettingsCommand::LossModel(loss_model)) => {
+                    loss_model.build()?;
+                }
+                _ => {}
+            }
+        }
+        //the settings model is checked even if a custom one replaces it, as it can be restored later
+        let settings_loss_model = self.settings.loss_model.build()?;
+
+        if self.seed.is_some() {
+            self.settings.seed = self.seed;
+        }
+
+        Ok(RustableDrone::from_builder(self, settings_loss_model))
+    }
+}
@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+
+/// Errors a [crate::RustableDrone] can encounter while being built or handling packets.
+/// What happens when one occurs while handling packets is decided by the [crate::ErrorPolicy] in the drone's settings.
+#[derive(Debug)]
+pub enum DroneError {
+    /// a packet drop rate is not between 0.0 and 1.0
+    InvalidDropRate(f32),
+    /// the hop_index of the routing header is out of bounds ( < 1 or >= len )
+    InvalidHopIndex(SourceRoutingHeader),
+    /// there is no channel to the adjacent node a packet has to be sent to
+    MissingChannel(NodeId),
+    /// the `send` method of the channel to an adjacent node returned an error
+    PacketSendFailed(NodeId),
+    /// the `send` method of the channel to the controller returned an error
+    EventSendFailed,
+    /// every sender of the packet channel has been dropped
+    PacketChannelDisconnected,
+    /// every sender of the controller's command channel has been dropped
+    CommandChannelDisconnected,
+}
+
+impl Display for DroneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DroneError::InvalidDropRate(pdr) => {
+                write!(f, "invalid packet drop rate value: {}", pdr)
+            }
+            DroneError::InvalidHopIndex(header) => {
+                write!(f, "hop_index {} is out of bounds in routing header {:?}", header.hop_index, header.hops)
+            }
+            DroneError::MissingChannel(id) => {
+                write!(f, "the channel to node {} does not exist", id)
+            }
+            DroneError::PacketSendFailed(id) => {
+                write!(f, "could not send a packet to node {}, as the channel is disconnected", id)
+            }
+            DroneError::EventSendFailed => {
+                write!(f, "could not send an event to the controller, as the channel is disconnected")
+            }
+            DroneError::PacketChannelDisconnected => {
+                write!(f, "the packet channel is disconnected")
+            }
+            DroneError::CommandChannelDisconnected => {
+                write!(f, "the command channel is disconnected")
+            }
+        }
+    }
+}
+
+impl Error for DroneError {}
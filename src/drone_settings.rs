@@ -1,30 +1,154 @@
+use std::collections::HashMap;
 use std::time::Duration;
+use wg_2024::network::NodeId;
+use crate::latency::LatencyDistribution;
+use crate::loss_model::LossModelType;
+use crate::payload_mutation::PayloadMutation;
 
 /// Set of rules the drone follows
 /// log_to_stdout:                  if true, prints to console every sent/received/dropped packet (default: false).
-/// sleep_duration:                 interval of time the drone sleeps before forwarding a packet. (default: ZERO)
-/// await_queued_packets_on_crash:  if true, when a crash command is received, waits for all received packets to be processed before crashing. (default: true)
-/// filter_packets:                 if true, filters fragment packets according to the current filter (default: true).
-/// send_nack_on_filtered_packet:   if true, when a packet is filtered and not passed through, sends back a NACK. might (and will) cause loops. (default: false)
-/// quack:                          if true, quacks the message. (default: false)
+/// latency:                        distribution of the interval of time a packet is held before being forwarded, without blocking the drone. (default: Fixed(ZERO))
+/// link_latencies:                 latency distributions of single outgoing links, overriding the drone's one. (default: empty)
+/// await_queued_packets_on_crash:  if true, when a crash command is received, keeps handling packets as a crashing drone until every sender is removed. (default: true)
+/// filter_packets:                 if true, filters packets according to the current filter (default: true).
+/// send_nack_on_filtered_packet:   if true, when a fragment is filtered and not passed through, sends back a NACK. might (and will) cause loops. (default: false)
+/// payload_mutation:               how the payload of forwarded fragments is changed, None leaves it untouched. (default: None)
+/// error_policy:                   what the drone does when an error occurs while handling a packet. (default: Panic)
+/// flood_cache_capacity:           how many flood ids are remembered for every flood initiator. (default: 64)
+/// flood_cache_ttl:                how long a flood id is remembered, None means until it is pushed out by newer ones. (default: None)
+/// link_drop_rates:                packet drop rates of single links, overriding the drone's pdr. (default: empty)
+/// drop_rate_link:                 which link of a fragment is looked up in link_drop_rates. (default: Outgoing)
+/// loss_model:                     how the drop decision is taken for every fragment. (default: Bernoulli)
+/// seed:                           seed of the drone's random number generator, None means a random seed. (default: None)
+/// duplicate_probability:          probability of forwarding a packet twice. (default: 0.0)
+/// reorder:                        how forwarded packets are held back so that later ones overtake them, None keeps arrival order. (default: None)
+/// disorder_acks_and_nacks:        if true, duplication and reordering also apply to forwarded ACKs and NACKs, not only to fragments. (default: false)
+/// rate_limit:                     token-bucket rate limit of the link to every adjacent node, None means unlimited. (default: None)
+/// link_rate_limits:               rate limits of single outgoing links, overriding rate_limit. (default: empty)
+/// scheduling:                     order in which received packets are handled. (default: Fifo)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct DroneSettings {
     pub log_to_stdout: bool,
-    pub sleep_duration: Duration,
+    pub latency: LatencyDistribution,
+    #[cfg_attr(feature = "serde", serde(with = "crate::config::node_id_map"))]
+    pub link_latencies: HashMap<NodeId, LatencyDistribution>,
     pub await_queued_packets_on_crash: bool,
     pub filter_packets: bool,
     pub send_nack_on_filtered_packet: bool,
-    pub quack: bool
+    pub payload_mutation: Option<PayloadMutation>,
+    pub error_policy: ErrorPolicy,
+    pub flood_cache_capacity: usize,
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub flood_cache_ttl: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::config::node_id_map"))]
+    pub link_drop_rates: HashMap<NodeId, f32>,
+    pub drop_rate_link: DropRateLink,
+    pub loss_model: LossModelType,
+    pub seed: Option<u64>,
+    pub duplicate_probability: f32,
+    pub reorder: Option<Reorder>,
+    pub disorder_acks_and_nacks: bool,
+    pub rate_limit: Option<RateLimit>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::config::node_id_map"))]
+    pub link_rate_limits: HashMap<NodeId, RateLimit>,
+    pub scheduling: SchedulingPolicy
+}
+
+/// What a drone does when an error occurs while handling a packet.
+/// Panic:              panics, killing the drone's thread.
+/// LogAndDrop:         prints the error to stderr and drops the packet.
+/// ReportToController: sends the packet to the controller as a [wg_2024::controller::DroneEvent::PacketDropped] and drops it.
+///                     prints the error to stderr if there is no packet to report or the controller is unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorPolicy {
+    Panic,
+    LogAndDrop,
+    ReportToController
+}
+
+/// Which link of a MsgFragment decides its packet drop rate.
+/// Outgoing: the link to the node the fragment is about to be forwarded to.
+/// Incoming: the link to the node the fragment arrived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DropRateLink {
+    Outgoing,
+    Incoming
+}
+
+/// Reordering of forwarded packets through jittered hold-back.
+/// probability: probability of holding back a packet, between 0.0 and 1.0.
+/// max_hold:    a held back packet is forwarded after a random delay up to this one, bounding how far it can be reordered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reorder {
+    pub probability: f32,
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub max_hold: Duration
+}
+
+/// Token-bucket rate limit of an outgoing link.
+/// packets_per_sec: rate tokens are gained at, every packet sent takes one.
+/// burst:           maximum number of tokens, that is how many packets can be sent back to back after an idle period (at least 1).
+/// queue_limit:     how many packets can wait for a token, the following ones are tail-dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RateLimit {
+    pub packets_per_sec: f64,
+    pub burst: u32,
+    pub queue_limit: usize
+}
+
+impl RateLimit {
+    /// returns the size of the bucket
+    pub(crate) fn capacity(&self) -> f64 {
+        self.burst.max(1) as f64
+    }
+}
+
+/// Order in which received packets are handled.
+/// Control packets are Acks, Nacks and FloodResponses, data packets are MsgFragments and FloodRequests.
+/// Fifo:               arrival order, as the protocol describes.
+/// StrictPriority:     control packets always go before data packets.
+/// WeightedRoundRobin: up to `control` control packets, then up to `data` data packets, and so on.
+///                     a class with weight 0 is only served when the other one has nothing waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SchedulingPolicy {
+    Fifo,
+    StrictPriority,
+    WeightedRoundRobin {
+        control: u32,
+        data: u32
+    }
 }
 
 impl Default for DroneSettings {
     fn default() -> Self {
         DroneSettings {
             log_to_stdout: false,
-            sleep_duration: Duration::ZERO,
+            latency: LatencyDistribution::default(),
+            link_latencies: HashMap::new(),
             await_queued_packets_on_crash: true,
             filter_packets: true,
             send_nack_on_filtered_packet: false,
-            quack: false
+            payload_mutation: None,
+            error_policy: ErrorPolicy::Panic,
+            flood_cache_capacity: 64,
+            flood_cache_ttl: None,
+            link_drop_rates: HashMap::new(),
+            drop_rate_link: DropRateLink::Outgoing,
+            loss_model: LossModelType::Bernoulli,
+            seed: None,
+            duplicate_probability: 0.0,
+            reorder: None,
+            disorder_acks_and_nacks: false,
+            rate_limit: None,
+            link_rate_limits: HashMap::new(),
+            scheduling: SchedulingPolicy::Fifo
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use std::time::Duration;
+use wg_2024::controller::DroneCommand;
+use wg_2024::network::NodeId;
+use crate::controller_commands::{FilterCommand, RustableCommand, SettingsCommand};
+
+/// Latest time a fault can be scheduled at, later times are capped to it
+pub const MAX_FAULT_TIME: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// A list of actions the drone performs on its own, at fixed times after the schedule is installed.
+/// The schedule set before running starts with `run()`, one set through a command starts when it is received.
+/// Actions with the same time are performed in insertion order.
+///
+/// ```ignore
+/// //drops everything coming from node 4 between t=5s and t=10s, then raises the pdr to 0.8 at t=12s
+/// let schedule = FaultSchedule::default()
+///     .window(Duration::from_secs(5), Duration::from_secs(10),
+///         FaultAction::Filter(FilterCommand::AddRule(drop_from_4)),
+///         FaultAction::Filter(FilterCommand::RemoveRule(drop_from_4_id)))
+///     .at(Duration::from_secs(12), FaultAction::SetPacketDropRate(0.8));
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct FaultSchedule {
+    faults: Vec<ScheduledFault>
+}
+
+/// An action of a [FaultSchedule] and the time it is performed at, relative to the start of the schedule
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduledFault {
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub at: Duration,
+    pub action: FaultAction
+}
+
+/// What a scheduled fault does, it acts as the corresponding command.
+/// SetPacketDropRate:  sets the drone's pdr.
+/// SetLinkDropRate:    sets the pdr of a single link.
+/// ResetLinkDropRate:  removes the pdr of a single link.
+/// Setting:            overwrites a setting.
+/// Filter:             changes the filter.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FaultAction {
+    SetPacketDropRate(f32),
+    SetLinkDropRate(NodeId, f32),
+    ResetLinkDropRate(NodeId),
+    Setting(SettingsCommand),
+    Filter(FilterCommand)
+}
+
+impl FaultSchedule {
+    /// returns the schedule with an action performed at the specified time
+    pub fn at(mut self, at: Duration, action: FaultAction) -> Self {
+        self.faults.push(ScheduledFault { at, action });
+        self
+    }
+
+    /// returns the schedule with an action performed at `start` and another one undoing it at `end`
+    pub fn window(self, start: Duration, end: Duration, action: FaultAction, undo: FaultAction) -> Self {
+        self.at(start, action).at(end, undo)
+    }
+
+    /// returns the scheduled faults, in insertion order
+    pub fn faults(&self) -> &[ScheduledFault] {
+        &self.faults
+    }
+
+    /// returns true if there are no scheduled faults
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+}
+
+impl FromIterator<ScheduledFault> for FaultSchedule {
+    fn from_iter<T: IntoIterator<Item=ScheduledFault>>(iter: T) -> Self {
+        FaultSchedule { faults: iter.into_iter().collect() }
+    }
+}
+
+impl IntoIterator for FaultSchedule {
+    type Item = ScheduledFault;
+    type IntoIter = std::vec::IntoIter<ScheduledFault>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.faults.into_iter()
+    }
+}
+
+impl From<FaultAction> for RustableCommand {
+    fn from(action: FaultAction) -> Self {
+        match action {
+            FaultAction::SetPacketDropRate(_pdr) => RustableCommand::DroneCommand(DroneCommand::SetPacketDropRate(_pdr)),
+            FaultAction::SetLinkDropRate(_id, _pdr) => RustableCommand::SetLinkDropRate(_id, _pdr),
+            FaultAction::ResetLinkDropRate(_id) => RustableCommand::ResetLinkDropRate(_id),
+            FaultAction::Setting(_command) => RustableCommand::SettingCommand(_command),
+            FaultAction::Filter(_command) => RustableCommand::FilterCommand(_command)
+        }
+    }
+}
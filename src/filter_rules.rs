@@ -0,0 +1,92 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{Packet, PacketType};
+use crate::packet_kind::{PacketKind, PacketKindMask};
+
+/// Identifier of a [FilterRule], chosen by whoever creates it
+pub type RuleId = u32;
+
+/// A rule of a [crate::PacketFilter]: packets matching it are handled according to its action.
+/// Rules are evaluated in order, and the first matching one wins.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterRule {
+    pub id: RuleId,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub matcher: RuleMatch,
+    pub action: RuleAction
+}
+
+/// Conditions a packet has to satisfy to match a rule. None matches any value.
+/// previous_hop:   the node the packet is coming from.
+/// next_hop:       the node the packet is going to be forwarded to. never matches FloodRequests, which go to every neighbor.
+/// destination:    the last node of the route. never matches FloodRequests.
+/// initiator:      the first node of the route, or the initiator of a FloodRequest.
+/// session_ids:    range of session ids.
+/// fragment_index: range of fragment indexes of MsgFragments, Acks and Nacks. never matches other packets.
+/// kinds:          kinds of packets. (default: all)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RuleMatch {
+    pub previous_hop: Option<NodeId>,
+    pub next_hop: Option<NodeId>,
+    pub destination: Option<NodeId>,
+    pub initiator: Option<NodeId>,
+    pub session_ids: Option<RangeInclusive<u64>>,
+    pub fragment_index: Option<RangeInclusive<u64>>,
+    pub kinds: PacketKindMask
+}
+
+/// What happens to a packet matching a rule.
+/// Allow:        the packet goes through, skipping the following rules.
+/// Drop:         the packet is filtered out.
+/// DropWithNack: the packet is filtered out and, if it is a MsgFragment, a NACK of type Dropped is sent back.
+/// Delay:        the packet is forwarded after the specified interval, on top of its latency.
+/// Corrupt:      if the packet is a MsgFragment, a random bit of its data is flipped before forwarding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RuleAction {
+    Allow,
+    Drop,
+    DropWithNack,
+    Delay(#[cfg_attr(feature = "serde", serde(with = "humantime_serde"))] Duration),
+    Corrupt
+}
+
+impl Default for RuleMatch {
+    fn default() -> Self {
+        RuleMatch {
+            previous_hop: None,
+            next_hop: None,
+            destination: None,
+            initiator: None,
+            session_ids: None,
+            fragment_index: None,
+            kinds: PacketKindMask::ALL
+        }
+    }
+}
+
+impl RuleMatch {
+    /// returns true if a packet coming from `from` and going to `to` satisfies every condition
+    pub fn matches(&self, packet: &Packet, from: NodeId, to: Option<NodeId>) -> bool {
+        let header = &packet.routing_header;
+        let (destination, initiator, fragment_index) = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => (header.hops.last(), header.hops.first(), Some(fragment.fragment_index)),
+            PacketType::Ack(ack) => (header.hops.last(), header.hops.first(), Some(ack.fragment_index)),
+            PacketType::Nack(nack) => (header.hops.last(), header.hops.first(), Some(nack.fragment_index)),
+            PacketType::FloodRequest(request) => (None, Some(&request.initiator_id), None),
+            PacketType::FloodResponse(_) => (header.hops.last(), header.hops.first(), None)
+        };
+
+        self.kinds.contains(PacketKind::of(&packet.pack_type))
+            && self.previous_hop.is_none_or(|id| id == from)
+            && self.next_hop.is_none_or(|id| to == Some(id))
+            && self.destination.is_none_or(|id| destination == Some(&id))
+            && self.initiator.is_none_or(|id| initiator == Some(&id))
+            && self.session_ids.as_ref().is_none_or(|range| range.contains(&packet.session_id))
+            && self.fragment_index.as_ref().is_none_or(|range| fragment_index.is_some_and(|index| range.contains(&index)))
+    }
+}
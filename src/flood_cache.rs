@@ -0,0 +1,182 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use wg_2024::network::NodeId;
+
+/// Remembers the flood ids received from each flood initiator, to recognize already visited FloodRequests.
+/// Every initiator has a ring buffer of at most `capacity` flood ids: once full, the oldest id is forgotten.
+/// If a `ttl` is set, flood ids older than it are forgotten as well.
+/// Lookups and insertions take O(1) (amortized).
+pub struct FloodIdCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    initiators: HashMap<NodeId, InitiatorFloods>
+}
+
+/// Flood ids of a single initiator, in insertion order
+#[derive(Default)]
+struct InitiatorFloods {
+    order: VecDeque<u64>,
+    ids: HashMap<u64, Instant>
+}
+
+impl FloodIdCache {
+    /// creates an empty cache. a capacity of 0 is treated as 1
+    pub fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+        FloodIdCache {
+            capacity: capacity.max(1),
+            ttl,
+            initiators: HashMap::new()
+        }
+    }
+
+    /// inserts a flood id of an initiator. returns false if it was already present, true otherwise
+    pub fn insert(&mut self, initiator: NodeId, flood_id: u64) -> bool {
+        let now = Instant::now();
+        let floods = self.initiators.entry(initiator).or_default();
+        floods.expire(self.ttl, now);
+
+        if floods.ids.contains_key(&flood_id) {
+            return false;
+        }
+
+        floods.order.push_back(flood_id);
+        floods.ids.insert(flood_id, now);
+        floods.truncate(self.capacity);
+        true
+    }
+
+    /// returns true if the flood id of an initiator is present
+    pub fn contains(&self, initiator: NodeId, flood_id: u64) -> bool {
+        match self.initiators.get(&initiator).and_then(|floods| floods.ids.get(&flood_id)) {
+            Some(inserted) => self.ttl.is_none_or(|ttl| inserted.elapsed() < ttl),
+            None => false
+        }
+    }
+
+    /// sets the maximum number of flood ids remembered for every initiator, forgetting the oldest ones if needed
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        for floods in self.initiators.values_mut() {
+            floods.truncate(self.capacity);
+        }
+    }
+
+    /// sets how long a flood id is remembered. None means forever
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+
+    /// forgets every flood id
+    pub fn clear(&mut self) {
+        self.initiators.clear();
+    }
+}
+
+impl InitiatorFloods {
+    /// forgets the flood ids older than the ttl
+    fn expire(&mut self, ttl: Option<Duration>, now: Instant) {
+        let Some(ttl) = ttl else {
+            return;
+        };
+
+        while let Some(id) = self.order.front() {
+            if now.duration_since(self.ids[id]) < ttl {
+                break;
+            }
+            self.ids.remove(id);
+            self.order.pop_front();
+        }
+    }
+
+    /// forgets the oldest flood ids until at most `capacity` are left
+    fn truncate(&mut self, capacity: usize) {
+        while self.order.len() > capacity {
+            if let Some(id) = self.order.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn repeated_flood_is_recognized() {
+        let mut cache = FloodIdCache::new(4, None);
+        assert!(cache.insert(1, 7));
+        assert!(!cache.insert(1, 7));
+        assert!(cache.contains(1, 7));
+
+        //the same flood id of another initiator is another flood
+        assert!(cache.insert(2, 7));
+        assert!(!cache.contains(3, 7));
+    }
+
+    #[test]
+    fn oldest_flood_is_evicted_when_full() {
+        let mut cache = FloodIdCache::new(3, None);
+        for flood_id in 0..4 {
+            assert!(cache.insert(1, flood_id));
+        }
+
+        assert!(!cache.contains(1, 0));
+        assert!((1..4).all(|flood_id| cache.contains(1, flood_id)));
+        //an evicted flood is new again
+        assert!(cache.insert(1, 0));
+        assert!(!cache.contains(1, 1));
+    }
+
+    #[test]
+    fn capacity_is_per_initiator() {
+        let mut cache = FloodIdCache::new(2, None);
+        cache.insert(1, 0);
+        cache.insert(1, 1);
+        cache.insert(2, 0);
+        cache.insert(2, 1);
+
+        assert!(cache.contains(1, 0) && cache.contains(2, 0));
+    }
+
+    #[test]
+    fn shrinking_the_capacity_forgets_the_oldest_floods() {
+        let mut cache = FloodIdCache::new(4, None);
+        for flood_id in 0..4 {
+            cache.insert(1, flood_id);
+        }
+        cache.set_capacity(2);
+
+        assert!(!cache.contains(1, 0) && !cache.contains(1, 1));
+        assert!(cache.contains(1, 2) && cache.contains(1, 3));
+    }
+
+    #[test]
+    fn zero_capacity_remembers_the_last_flood() {
+        let mut cache = FloodIdCache::new(0, None);
+        cache.insert(1, 0);
+        assert!(!cache.insert(1, 0));
+        cache.insert(1, 1);
+        assert!(!cache.contains(1, 0));
+    }
+
+    #[test]
+    fn flood_expires_after_the_ttl() {
+        let mut cache = FloodIdCache::new(4, Some(Duration::from_millis(20)));
+        cache.insert(1, 0);
+        assert!(cache.contains(1, 0));
+
+        thread::sleep(Duration::from_millis(30));
+        assert!(!cache.contains(1, 0));
+        assert!(cache.insert(1, 0));
+    }
+
+    #[test]
+    fn clear_forgets_every_flood() {
+        let mut cache = FloodIdCache::new(4, None);
+        cache.insert(1, 0);
+        cache.clear();
+        assert!(cache.insert(1, 0));
+    }
+}
@@ -0,0 +1,82 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+use rand::{Rng, RngCore};
+
+/// Upper bound of every sampled latency, so that heavy-tailed distributions can't hold a packet forever
+pub const MAX_LATENCY: Duration = Duration::from_secs(3600);
+
+/// Distribution the delay of every forwarded packet is sampled from.
+/// Fixed:       always the same delay, no random value is drawn.
+/// Uniform:     any delay between `min` and `max`, with the same probability.
+/// Normal:      a gaussian around `mean`, negative samples become zero.
+/// Exponential: delays with the specified mean, short ones being the most likely.
+/// Pareto:      heavy-tailed delays, never below `scale`. the lower the `shape`, the longer the tail.
+/// Samples come from the drone's random number generator, so they are reproducible with a seed.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LatencyDistribution {
+    Fixed(#[cfg_attr(feature = "serde", serde(with = "humantime_serde"))] Duration),
+    Uniform {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        min: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        max: Duration
+    },
+    Normal {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        mean: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        std_dev: Duration
+    },
+    Exponential {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        mean: Duration
+    },
+    Pareto {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        scale: Duration,
+        shape: f64
+    }
+}
+
+impl Default for LatencyDistribution {
+    fn default() -> Self {
+        LatencyDistribution::Fixed(Duration::ZERO)
+    }
+}
+
+impl LatencyDistribution {
+    /// samples a delay, capped at [MAX_LATENCY]
+    pub fn sample(&self, rng: &mut dyn RngCore) -> Duration {
+        let secs = match self {
+            LatencyDistribution::Fixed(_delay) => {
+                return (*_delay).min(MAX_LATENCY);
+            }
+            LatencyDistribution::Uniform { min, max } => {
+                if max <= min {
+                    return (*min).min(MAX_LATENCY);
+                }
+                return rng.gen_range(*min..=*max).min(MAX_LATENCY);
+            }
+            LatencyDistribution::Normal { mean, std_dev } => {
+                //Box-Muller transform, u1 in (0, 1] so that its logarithm is finite
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+                mean.as_secs_f64() + z * std_dev.as_secs_f64()
+            }
+            LatencyDistribution::Exponential { mean } => {
+                //inverse transform sampling
+                let u = 1.0 - rng.gen::<f64>();
+                -mean.as_secs_f64() * u.ln()
+            }
+            LatencyDistribution::Pareto { scale, shape } => {
+                //inverse transform sampling
+                let u = 1.0 - rng.gen::<f64>();
+                scale.as_secs_f64() / u.powf(1.0 / shape)
+            }
+        };
+
+        Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(MAX_LATENCY).min(MAX_LATENCY)
+    }
+}
@@ -1,10 +1,41 @@
+#[macro_use]
+mod log;
+mod capture;
+mod delay_queue;
 mod drone;
+mod drone_builder;
+mod drone_error;
 mod drone_settings;
+mod fault_schedule;
+mod filter_rules;
+mod flood_cache;
+mod latency;
+mod loss_model;
+mod packet_kind;
 mod packets_filter;
+mod payload_mutation;
+mod rate_limiter;
+mod scheduler;
+mod stats;
 mod controller_commands;
+#[cfg(feature = "serde")]
+pub mod config;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 
+pub use capture::*;
 pub use drone::*;
+pub use drone_builder::*;
+pub use drone_error::*;
 pub use drone_settings::*;
+pub use fault_schedule::*;
+pub use filter_rules::*;
+pub use flood_cache::*;
+pub use latency::*;
+pub use loss_model::*;
+pub use packet_kind::*;
 pub use packets_filter::*;
+pub use payload_mutation::*;
+pub use stats::*;
 pub use controller_commands::*;
\ No newline at end of file
@@ -0,0 +1,73 @@
+/// Logs something a drone did.
+/// With the `tracing` feature it is a structured event at the specified level, carrying the drone id and the specified fields,
+/// otherwise it is a line on stdout, printed only if `log_to_stdout` is set.
+///
+/// Field values are only evaluated when the event is logged. Values without a `tracing::Value` impl are passed
+/// through [debug_field], instead of being formatted into a `String` beforehand.
+///
+/// ```ignore
+/// drone_log!(debug, self, from = from, to = to; "received a MsgFragment from node {} and forwarded it to node {}", from, to);
+/// drone_log!(info, self, fault = debug_field(&action); "performed the scheduled fault {:?}", action);
+/// ```
+macro_rules! drone_log {
+    ($level:ident, $drone:expr $(, $field:ident = $value:expr)* ; $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!(drone_id = $drone.id $(, $field = $value)*, $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        {
+            if $drone.settings.log_to_stdout {
+                //fields are only part of structured events
+                let _ = ($(&$value,)*);
+                println!("RustableDrone {} {}", $drone.id, format_args!($($arg)+));
+            }
+        }
+    };
+}
+
+/// Logs an error of a drone, regardless of `log_to_stdout`.
+/// With the `tracing` feature it is a structured event at the error level, otherwise it is a line on stderr.
+macro_rules! drone_error {
+    ($drone:expr $(, $field:ident = $value:expr)* ; $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::error!(drone_id = $drone.id $(, $field = $value)*, $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = ($(&$value,)*);
+            eprintln!("RustableDrone {} {}", $drone.id, format_args!($($arg)+));
+        }
+    };
+}
+
+/// Wraps a field value so that it is recorded with its Debug representation, only if the event is logged
+#[cfg(feature = "tracing")]
+pub(crate) fn debug_field<T: std::fmt::Debug>(value: T) -> tracing::field::DebugValue<T> {
+    tracing::field::debug(value)
+}
+
+/// Without the `tracing` feature fields are not printed, the value is left as it is
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn debug_field<T: std::fmt::Debug>(value: T) -> T {
+    value
+}
+
+/// Installs a subscriber printing every event to stdout as the default of the drone's thread, while `log_to_stdout` is set
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+pub(crate) struct StdoutSubscriber {
+    guard: Option<tracing::subscriber::DefaultGuard>
+}
+
+#[cfg(feature = "tracing")]
+impl StdoutSubscriber {
+    /// installs or removes the subscriber, following the setting
+    pub(crate) fn sync(&mut self, log_to_stdout: bool) {
+        if log_to_stdout && self.guard.is_none() {
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::TRACE)
+                .finish();
+            self.guard = Some(tracing::subscriber::set_default(subscriber));
+        } else if !log_to_stdout {
+            self.guard = None;
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use rand::{Rng, RngCore};
+use crate::drone_error::DroneError;
+
+/// Decides whether a MsgFragment is dropped.
+/// A model is owned by a single drone and may keep state across packets, to simulate correlated losses.
+pub trait LossModel: Send {
+    /// returns true if the fragment has to be dropped. `pdr` is the packet drop rate of the link it travels on
+    fn should_drop(&mut self, pdr: f32, rng: &mut dyn RngCore) -> bool;
+}
+
+/// Independent losses: every fragment is dropped with probability pdr.
+/// This is the behavior described by the protocol.
+#[derive(Default)]
+pub struct Bernoulli;
+
+impl LossModel for Bernoulli {
+    fn should_drop(&mut self, pdr: f32, rng: &mut dyn RngCore) -> bool {
+        rng.gen_bool(pdr as f64)
+    }
+}
+
+/// Bursty losses: a two-state Markov chain, alternating between a Good and a Bad state.
+/// In the Good state fragments are dropped with probability pdr, in the Bad state with probability `bad_drop_rate`.
+/// Before deciding on each fragment, the model moves from Good to Bad with probability `p_good_to_bad`
+/// and from Bad to Good with probability `p_bad_to_good`.
+pub struct GilbertElliott {
+    p_good_to_bad: f64,
+    p_bad_to_good: f64,
+    bad_drop_rate: f64,
+    bad: bool
+}
+
+impl GilbertElliott {
+    /// creates a model starting in the Good state.
+    /// returns an InvalidDropRate error if a probability is not between 0.0 and 1.0, NaN included
+    pub fn new(p_good_to_bad: f64, p_bad_to_good: f64, bad_drop_rate: f64) -> Result<Self, DroneError> {
+        if let Some(p) = [p_good_to_bad, p_bad_to_good, bad_drop_rate].into_iter().find(|p| !(0.0..=1.0).contains(p)) {
+            return Err(DroneError::InvalidDropRate(p as f32));
+        }
+
+        Ok(GilbertElliott {
+            p_good_to_bad,
+            p_bad_to_good,
+            bad_drop_rate,
+            bad: false
+        })
+    }
+
+    /// returns true if the model is in the Bad state
+    pub fn is_bad(&self) -> bool {
+        self.bad
+    }
+}
+
+impl LossModel for GilbertElliott {
+    fn should_drop(&mut self, pdr: f32, rng: &mut dyn RngCore) -> bool {
+        let transition = if self.bad { self.p_bad_to_good } else { self.p_good_to_bad };
+        if rng.gen_bool(transition) {
+            self.bad = !self.bad;
+        }
+
+        if self.bad {
+            rng.gen_bool(self.bad_drop_rate)
+        } else {
+            rng.gen_bool(pdr as f64)
+        }
+    }
+}
+
+/// Loss models that can be selected through the settings.
+/// Bernoulli:      see [Bernoulli].
+/// GilbertElliott: see [GilbertElliott].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LossModelType {
+    Bernoulli,
+    GilbertElliott {
+        p_good_to_bad: f64,
+        p_bad_to_good: f64,
+        bad_drop_rate: f64
+    }
+}
+
+impl LossModelType {
+    /// creates a new model of this type, in its initial state.
+    /// returns an InvalidDropRate error if one of its probabilities is invalid
+    pub fn build(&self) -> Result<Box<dyn LossModel>, DroneError> {
+        match self {
+            LossModelType::Bernoulli => Ok(Box::new(Bernoulli)),
+            LossModelType::GilbertElliott { p_good_to_bad, p_bad_to_good, bad_drop_rate } => {
+                Ok(Box::new(GilbertElliott::new(*p_good_to_bad, *p_bad_to_good, *bad_drop_rate)?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn gilbert_elliott_rejects_nan() {
+        assert!(GilbertElliott::new(f64::NAN, 0.5, 0.5).is_err());
+        assert!(GilbertElliott::new(0.5, f64::NAN, 0.5).is_err());
+        assert!(matches!(GilbertElliott::new(0.5, 0.5, f64::NAN), Err(DroneError::InvalidDropRate(p)) if p.is_nan()));
+    }
+
+    #[test]
+    fn gilbert_elliott_rejects_out_of_range() {
+        assert!(matches!(GilbertElliott::new(-0.1, 0.5, 0.5), Err(DroneError::InvalidDropRate(p)) if p == -0.1));
+        assert!(matches!(GilbertElliott::new(0.5, 1.5, 0.5), Err(DroneError::InvalidDropRate(p)) if p == 1.5));
+        assert!(GilbertElliott::new(0.5, 0.5, f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn gilbert_elliott_accepts_the_bounds() {
+        let mut model = GilbertElliott::new(1.0, 0.0, 1.0).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        //moves to the Bad state on the first fragment and never leaves it
+        assert!(model.should_drop(0.0, &mut rng));
+        assert!(model.is_bad());
+        assert!(model.should_drop(0.0, &mut rng));
+    }
+
+    #[test]
+    fn loss_model_type_build_checks_the_probabilities() {
+        assert!(LossModelType::Bernoulli.build().is_ok());
+        assert!(LossModelType::GilbertElliott { p_good_to_bad: 0.1, p_bad_to_good: 0.5, bad_drop_rate: 0.9 }.build().is_ok());
+        assert!(LossModelType::GilbertElliott { p_good_to_bad: f64::NAN, p_bad_to_good: 0.5, bad_drop_rate: 0.9 }.build().is_err());
+    }
+}
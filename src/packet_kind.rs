@@ -0,0 +1,94 @@
+use wg_2024::packet::PacketType;
+
+/// The kind of a [PacketType], without its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PacketKind {
+    MsgFragment,
+    Ack,
+    Nack,
+    FloodRequest,
+    FloodResponse
+}
+
+impl PacketKind {
+    pub const ALL: [PacketKind; 5] = [
+        PacketKind::MsgFragment,
+        PacketKind::Ack,
+        PacketKind::Nack,
+        PacketKind::FloodRequest,
+        PacketKind::FloodResponse
+    ];
+
+    /// returns the kind of a packet type
+    pub fn of(packet_type: &PacketType) -> Self {
+        match packet_type {
+            PacketType::MsgFragment(_) => PacketKind::MsgFragment,
+            PacketType::Ack(_) => PacketKind::Ack,
+            PacketType::Nack(_) => PacketKind::Nack,
+            PacketType::FloodRequest(_) => PacketKind::FloodRequest,
+            PacketType::FloodResponse(_) => PacketKind::FloodResponse
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A set of [PacketKind]s.
+/// Serialized as a list of kinds, eg: `["MsgFragment", "FloodRequest"]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<PacketKind>", into = "Vec<PacketKind>"))]
+pub struct PacketKindMask(u8);
+
+impl PacketKindMask {
+    /// no kind at all
+    pub const NONE: PacketKindMask = PacketKindMask(0);
+    /// every kind
+    pub const ALL: PacketKindMask = PacketKindMask(0b11111);
+
+    /// a mask containing a single kind
+    pub fn only(kind: PacketKind) -> Self {
+        PacketKindMask(kind.bit())
+    }
+
+    /// returns the mask with a kind added
+    pub fn with(self, kind: PacketKind) -> Self {
+        PacketKindMask(self.0 | kind.bit())
+    }
+
+    /// returns the mask with a kind removed
+    pub fn without(self, kind: PacketKind) -> Self {
+        PacketKindMask(self.0 & !kind.bit())
+    }
+
+    /// returns true if the mask contains a kind
+    pub fn contains(self, kind: PacketKind) -> bool {
+        self.0 & kind.bit() != 0
+    }
+
+    /// returns true if the mask contains no kind
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl FromIterator<PacketKind> for PacketKindMask {
+    fn from_iter<T: IntoIterator<Item=PacketKind>>(iter: T) -> Self {
+        iter.into_iter().fold(PacketKindMask::NONE, PacketKindMask::with)
+    }
+}
+
+impl From<Vec<PacketKind>> for PacketKindMask {
+    fn from(kinds: Vec<PacketKind>) -> Self {
+        kinds.into_iter().collect()
+    }
+}
+
+impl From<PacketKindMask> for Vec<PacketKind> {
+    fn from(mask: PacketKindMask) -> Self {
+        PacketKind::ALL.into_iter().filter(|kind| mask.contains(*kind)).collect()
+    }
+}
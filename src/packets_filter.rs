@@ -1,17 +1,33 @@
+use std::collections::HashMap;
 use wg_2024::network::NodeId;
+use wg_2024::packet::Packet;
+use crate::filter_rules::{FilterRule, RuleAction, RuleId};
+use crate::packet_kind::{PacketKind, PacketKindMask};
 use crate::packets_filter::FilterType::{BlackList, WhiteList};
 
-/// Filters packets of type [MsgFragment] based on the node they are coming from.
+/// Filters packets based on the node they are coming from and on their kind.
 /// Effectively, this is a 100% drop probability on packets coming from specified adjacent drone IDs.
 /// Can be set as a WhiteList (allows only packets from drones in the list).
 /// or as a BlackList (allows packets from every drone not in the list).
+/// Every node in the list is matched only on the packet kinds of its mask, which defaults to `kinds`.
+/// Packets of kinds outside `kinds` are always allowed by a WhiteList.
+/// Before the list, packets are checked against an ordered list of [FilterRule]s, the first matching one decides.
 /// Note that the filter is applied after the probability to drop the packet.
-/// Default value is an empty BlackList (everything passes).
+/// Default value is an empty BlackList on MsgFragments (everything passes).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct PacketFilter {
     list: Vec<NodeId>,
-    filter_type: FilterType
+    filter_type: FilterType,
+    kinds: PacketKindMask,
+    #[cfg_attr(feature = "serde", serde(with = "crate::config::node_id_map"))]
+    node_kinds: HashMap<NodeId, PacketKindMask>,
+    rules: Vec<FilterRule>
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilterType {
     BlackList,
     WhiteList
@@ -21,7 +37,10 @@ impl Default for PacketFilter {
     fn default() -> Self {
         PacketFilter {
             list: vec![],
-            filter_type: BlackList
+            filter_type: BlackList,
+            kinds: PacketKindMask::only(PacketKind::MsgFragment),
+            node_kinds: HashMap::new(),
+            rules: vec![]
         }
     }
 }
@@ -34,38 +53,86 @@ impl PacketFilter {
         }
     }
 
+    /// adds NodeId to the internal list, matching only the packet kinds in the mask
+    pub fn add_with_kinds(&mut self, id: NodeId, kinds: PacketKindMask) {
+        self.add(id);
+        self.node_kinds.insert(id, kinds);
+    }
+
     /// removes a NodeId from the internal list
     pub fn remove(&mut self, id: NodeId) {
-        let p = self.list.iter().position(|&x| x == id);
-        if p.is_some() {
-            self.list.remove(p.unwrap());
+        if let Some(p) = self.list.iter().position(|&x| x == id) {
+            self.list.remove(p);
         }
+        self.node_kinds.remove(&id);
     }
 
-    /// returns true if a NodeId is allowed to send a packet, false if it isn't
-    pub fn is_allowed(&self, id: NodeId) -> bool {
+    /// returns true if a NodeId is allowed to send a packet of the specified kind, false if it isn't
+    pub fn is_allowed(&self, id: NodeId, kind: PacketKind) -> bool {
+        let listed = self.list.contains(&id) && self.node_kinds.get(&id).unwrap_or(&self.kinds).contains(kind);
         match self.filter_type {
             BlackList => {
-                !self.list.contains(&id)
+                !listed
             }
             WhiteList => {
-                self.list.contains(&id)
+                listed || !self.kinds.contains(kind)
             }
         }
     }
 
-    /// clears the filter
+    /// clears the internal list, rules are kept
     pub fn clear(&mut self) {
         self.list.clear();
+        self.node_kinds.clear();
     }
 
     /// set the internal list of node ids
     pub fn set(&mut self, list: Vec<NodeId>) {
-        self.list = list.clone();
+        self.node_kinds.retain(|id, _| list.contains(id));
+        self.list = list;
     }
 
     /// set the filter type
     pub fn set_type(&mut self, t: FilterType) {
         self.filter_type = t;
     }
-}
\ No newline at end of file
+
+    /// set the packet kinds the filter applies to, for nodes added without a mask
+    pub fn set_kinds(&mut self, kinds: PacketKindMask) {
+        self.kinds = kinds;
+    }
+
+    /// appends a rule, replacing the one with the same id if present
+    pub fn add_rule(&mut self, rule: FilterRule) {
+        self.remove_rule(rule.id);
+        self.rules.push(rule);
+    }
+
+    /// inserts a rule at the specified position (or at the end if out of bounds), replacing the one with the same id if present
+    pub fn insert_rule(&mut self, index: usize, rule: FilterRule) {
+        self.remove_rule(rule.id);
+        self.rules.insert(index.min(self.rules.len()), rule);
+    }
+
+    /// removes the rule with the specified id
+    pub fn remove_rule(&mut self, id: RuleId) {
+        self.rules.retain(|rule| rule.id != id);
+    }
+
+    /// removes every rule
+    pub fn clear_rules(&mut self) {
+        self.rules.clear();
+    }
+
+    /// returns the rules, in evaluation order
+    pub fn rules(&self) -> &[FilterRule] {
+        &self.rules
+    }
+
+    /// returns the action of the first rule matching a packet coming from `from` and going to `to`, if any
+    pub fn rule_action(&self, packet: &Packet, from: NodeId, to: Option<NodeId>) -> Option<RuleAction> {
+        self.rules.iter()
+            .find(|rule| rule.matcher.matches(packet, from, to))
+            .map(|rule| rule.action)
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use rand::{Rng, RngCore};
+use wg_2024::packet::Fragment;
+
+/// The pattern a quacking drone writes over every fragment
+const QUACK: &[u8] = b"QUACK";
+
+/// A user-supplied function changing a fragment, see [MutationMode::Custom]
+pub type MutationFn = Arc<dyn Fn(&mut Fragment, &mut dyn RngCore) + Send + Sync>;
+
+/// Changes the payload of forwarded fragments, so that clients can test their integrity checks.
+/// mode:        how the payload is changed.
+/// probability: probability of changing the payload of a fragment, between 0.0 and 1.0.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PayloadMutation {
+    pub mode: MutationMode,
+    pub probability: f32
+}
+
+/// How a [PayloadMutation] changes a fragment.
+/// BitFlips:  flips the specified number of random bits within `length`.
+/// ZeroBytes: sets the specified number of random bytes within `length` to zero.
+/// Truncate:  lowers `length` to a random smaller value, the data is left untouched.
+/// Pattern:   overwrites the whole data repeating the pattern, eg: "QUACK".
+/// Custom:    calls a user-supplied closure. can't be written in configuration files.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MutationMode {
+    BitFlips(u32),
+    ZeroBytes(u32),
+    Truncate,
+    Pattern(Vec<u8>),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(MutationFn)
+}
+
+impl PayloadMutation {
+    /// a mutation applied to every fragment
+    pub fn always(mode: MutationMode) -> Self {
+        PayloadMutation { mode, probability: 1.0 }
+    }
+
+    /// the drone becomes a duck and quacks every fragment
+    pub fn quack() -> Self {
+        PayloadMutation::always(MutationMode::Pattern(QUACK.to_vec()))
+    }
+
+    /// returns true if this is the mutation installed by [PayloadMutation::quack]
+    pub fn is_quack(&self) -> bool {
+        self.probability >= 1.0 && matches!(&self.mode, MutationMode::Pattern(pattern) if pattern == QUACK)
+    }
+
+    /// changes the fragment with the mutation's probability
+    pub fn apply(&self, fragment: &mut Fragment, rng: &mut dyn RngCore) {
+        if rng.gen::<f32>() < self.probability {
+            self.mode.apply(fragment, rng);
+        }
+    }
+}
+
+impl MutationMode {
+    /// changes the fragment
+    pub fn apply(&self, fragment: &mut Fragment, rng: &mut dyn RngCore) {
+        let len = (fragment.length as usize).min(fragment.data.len());
+        match self {
+            MutationMode::BitFlips(_count) => {
+                if len > 0 {
+                    for _ in 0..*_count {
+                        let bit = rng.gen_range(0..len * 8);
+                        fragment.data[bit / 8] ^= 1 << (bit % 8);
+                    }
+                }
+            }
+            MutationMode::ZeroBytes(_count) => {
+                if len > 0 {
+                    for _ in 0..*_count {
+                        fragment.data[rng.gen_range(0..len)] = 0;
+                    }
+                }
+            }
+            MutationMode::Truncate => {
+                if len > 0 {
+                    fragment.length = rng.gen_range(0..len) as u8;
+                }
+            }
+            MutationMode::Pattern(_pattern) => {
+                if !_pattern.is_empty() {
+                    for (i, byte) in fragment.data.iter_mut().enumerate() {
+                        *byte = _pattern[i % _pattern.len()];
+                    }
+                }
+            }
+            MutationMode::Custom(_mutate) => {
+                _mutate(fragment, rng);
+            }
+        }
+    }
+}
+
+impl Debug for MutationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MutationMode::BitFlips(count) => f.debug_tuple("BitFlips").field(count).finish(),
+            MutationMode::ZeroBytes(count) => f.debug_tuple("ZeroBytes").field(count).finish(),
+            MutationMode::Truncate => f.write_str("Truncate"),
+            MutationMode::Pattern(pattern) => f.debug_tuple("Pattern").field(pattern).finish(),
+            MutationMode::Custom(_) => f.write_str("Custom(..)")
+        }
+    }
+}
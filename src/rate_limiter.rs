@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+use wg_2024::network::NodeId;
+use crate::drone_settings::RateLimit;
+
+/// Token buckets and queues of the rate limited links of a drone.
+/// Links without a limit are not tracked, unless items are still queued from when they had one.
+/// Links are visited in NodeId order, so that seeded runs release queued items in the same order.
+pub struct RateLimiter<T> {
+    links: BTreeMap<NodeId, Link<T>>
+}
+
+/// The bucket and the queue of a single link
+struct Link<T> {
+    tokens: f64,
+    last_refill: Instant,
+    queue: VecDeque<T>
+}
+
+/// What happens to an item offered to a link
+pub enum Admission<T> {
+    /// there is a token for it, the item can be sent right away
+    Send(T),
+    /// the item waits in the link's queue
+    Queued,
+    /// the link's queue is full, the item is returned to be dropped
+    Rejected(T)
+}
+
+impl<T> Default for RateLimiter<T> {
+    fn default() -> Self {
+        RateLimiter {
+            links: BTreeMap::new()
+        }
+    }
+}
+
+impl<T> Link<T> {
+    /// creates a link with a full bucket
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        Link {
+            tokens: limit.capacity(),
+            last_refill: now,
+            queue: VecDeque::new()
+        }
+    }
+
+    /// adds the tokens gained since the last refill
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.packets_per_sec.max(0.0)).min(limit.capacity());
+        self.last_refill = now;
+    }
+
+    /// takes a token if there is one, an unlimited link always has one
+    fn take_token(&mut self, limit: Option<&RateLimit>, now: Instant) -> bool {
+        let Some(limit) = limit else { return true };
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return true;
+        }
+        false
+    }
+
+    /// returns when the next token is available, None if never
+    fn next_token_at(&self, limit: Option<&RateLimit>, now: Instant) -> Option<Instant> {
+        let Some(limit) = limit else { return Some(now) };
+        if self.tokens >= 1.0 {
+            return Some(now);
+        }
+        if limit.packets_per_sec <= 0.0 {
+            return None;
+        }
+        let wait = Duration::try_from_secs_f64((1.0 - self.tokens) / limit.packets_per_sec).ok()?;
+        self.last_refill.checked_add(wait)
+    }
+}
+
+impl<T> RateLimiter<T> {
+    /// offers an item to a link, with its current limit
+    pub fn admit(&mut self, to: NodeId, limit: Option<&RateLimit>, item: T, now: Instant) -> Admission<T> {
+        let link = match (self.links.get_mut(&to), limit) {
+            (Some(link), _) => link,
+            (None, Some(limit)) => self.links.entry(to).or_insert_with(|| Link::new(limit, now)),
+            (None, None) => return Admission::Send(item)
+        };
+
+        //queued items go first, to keep the order
+        if link.queue.is_empty() && link.take_token(limit, now) {
+            return Admission::Send(item);
+        }
+        if link.queue.len() >= limit.map_or(usize::MAX, |limit| limit.queue_limit) {
+            return Admission::Rejected(item);
+        }
+        link.queue.push_back(item);
+        Admission::Queued
+    }
+
+    /// removes and returns a queued item whose link has a token for it
+    pub fn pop_ready(&mut self, limits: impl Fn(NodeId) -> Option<RateLimit>, now: Instant) -> Option<(NodeId, T)> {
+        for (id, link) in self.links.iter_mut() {
+            if !link.queue.is_empty() && link.take_token(limits(*id).as_ref(), now) {
+                return link.queue.pop_front().map(|item| (*id, item));
+            }
+        }
+        None
+    }
+
+    /// returns the earliest time a queued item can be sent
+    pub fn next_deadline(&self, limits: impl Fn(NodeId) -> Option<RateLimit>, now: Instant) -> Option<Instant> {
+        self.links.iter()
+            .filter(|(_, link)| !link.queue.is_empty())
+            .filter_map(|(id, link)| link.next_token_at(limits(*id).as_ref(), now))
+            .min()
+    }
+
+    /// removes and returns a queued item, regardless of the tokens
+    pub fn pop(&mut self) -> Option<(NodeId, T)> {
+        self.links.iter_mut()
+            .find_map(|(id, link)| link.queue.pop_front().map(|item| (*id, item)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit { packets_per_sec: 10.0, burst: 2, queue_limit: 2 };
+
+    fn limits(_id: NodeId) -> Option<RateLimit> {
+        Some(LIMIT)
+    }
+
+    #[test]
+    fn burst_is_sent_then_items_are_queued() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 0, now), Admission::Send(0)));
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 1, now), Admission::Send(1)));
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 2, now), Admission::Queued));
+        assert!(limiter.pop_ready(limits, now).is_none());
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for item in 0..4 {
+            limiter.admit(1, Some(&LIMIT), item, now);
+        }
+
+        //a token every 100ms
+        assert_eq!(limiter.next_deadline(limits, now), Some(now + Duration::from_millis(100)));
+        assert!(limiter.pop_ready(limits, now + Duration::from_millis(50)).is_none());
+        assert_eq!(limiter.pop_ready(limits, now + Duration::from_millis(100)), Some((1, 2)));
+        assert!(limiter.pop_ready(limits, now + Duration::from_millis(100)).is_none());
+        assert_eq!(limiter.pop_ready(limits, now + Duration::from_millis(200)), Some((1, 3)));
+        assert_eq!(limiter.next_deadline(limits, now + Duration::from_millis(200)), None);
+    }
+
+    #[test]
+    fn refill_does_not_exceed_the_burst() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        limiter.admit(1, Some(&LIMIT), 0, now);
+
+        let later = now + Duration::from_secs(10);
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 1, later), Admission::Send(1)));
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 2, later), Admission::Send(2)));
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 3, later), Admission::Queued));
+    }
+
+    #[test]
+    fn full_queue_rejects_items() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for item in 0..4 {
+            limiter.admit(1, Some(&LIMIT), item, now);
+        }
+
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 4, now), Admission::Rejected(4)));
+        //another link has its own queue
+        assert!(matches!(limiter.admit(2, Some(&LIMIT), 5, now), Admission::Send(5)));
+    }
+
+    #[test]
+    fn queued_items_keep_their_order() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for item in 0..4 {
+            limiter.admit(1, Some(&LIMIT), item, now);
+        }
+
+        //a token is available again, but the queued items go first
+        let later = now + Duration::from_millis(100);
+        assert!(matches!(limiter.admit(1, Some(&LIMIT), 4, later), Admission::Rejected(4)));
+        assert_eq!(limiter.pop_ready(limits, later), Some((1, 2)));
+    }
+
+    #[test]
+    fn unlimited_link_sends_right_away() {
+        let mut limiter = RateLimiter::default();
+        assert!(matches!(limiter.admit(1, None, 0, Instant::now()), Admission::Send(0)));
+        assert!(limiter.pop().is_none());
+    }
+
+    #[test]
+    fn links_release_in_node_id_order() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for to in [9, 3, 7, 1] {
+            limiter.admit(to, Some(&LIMIT), 0, now);
+            limiter.admit(to, Some(&LIMIT), 1, now);
+            limiter.admit(to, Some(&LIMIT), to as i32, now);
+        }
+
+        let later = now + Duration::from_millis(100);
+        let released: Vec<_> = std::iter::from_fn(|| limiter.pop_ready(limits, later)).collect();
+        assert_eq!(released, vec![(1, 1), (3, 3), (7, 7), (9, 9)]);
+    }
+
+    #[test]
+    fn pop_ignores_the_tokens() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for to in [2, 1] {
+            for item in 0..3 {
+                limiter.admit(to, Some(&LIMIT), item, now);
+            }
+        }
+
+        assert_eq!(limiter.pop(), Some((1, 2)));
+        assert_eq!(limiter.pop(), Some((2, 2)));
+        assert_eq!(limiter.pop(), None);
+    }
+}
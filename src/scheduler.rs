@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use wg_2024::packet::{Packet, PacketType};
+use crate::drone_settings::SchedulingPolicy;
+
+/// Class of a packet, for scheduling purposes.
+/// Control: Acks, Nacks and FloodResponses.
+/// Data:    MsgFragments and FloodRequests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketClass {
+    Control,
+    Data
+}
+
+impl PacketClass {
+    fn of(packet_type: &PacketType) -> Self {
+        match packet_type {
+            PacketType::Ack(_) | PacketType::Nack(_) | PacketType::FloodResponse(_) => PacketClass::Control,
+            PacketType::MsgFragment(_) | PacketType::FloodRequest(_) => PacketClass::Data
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            PacketClass::Control => PacketClass::Data,
+            PacketClass::Data => PacketClass::Control
+        }
+    }
+}
+
+/// Received packets waiting to be handled, split by class.
+/// Packets keep their arrival number, so that they can still be served in arrival order.
+pub struct PacketScheduler {
+    control: VecDeque<(u64, Packet)>,
+    data: VecDeque<(u64, Packet)>,
+    next_seq: u64,
+    //weighted round robin state: the class being served and how many of its packets were served in this turn
+    turn: PacketClass,
+    served: u32
+}
+
+impl Default for PacketScheduler {
+    fn default() -> Self {
+        PacketScheduler {
+            control: VecDeque::new(),
+            data: VecDeque::new(),
+            next_seq: 0,
+            turn: PacketClass::Control,
+            served: 0
+        }
+    }
+}
+
+impl PacketScheduler {
+    /// adds a received packet to the queue of its class
+    pub fn push(&mut self, packet: Packet) {
+        let item = (self.next_seq, packet);
+        self.next_seq += 1;
+        match PacketClass::of(&item.1.pack_type) {
+            PacketClass::Control => self.control.push_back(item),
+            PacketClass::Data => self.data.push_back(item)
+        }
+    }
+
+    /// returns true if no packet is waiting
+    pub fn is_empty(&self) -> bool {
+        self.control.is_empty() && self.data.is_empty()
+    }
+
+    /// removes and returns the next packet to handle according to the policy
+    pub fn pop(&mut self, policy: SchedulingPolicy) -> Option<Packet> {
+        let item = match policy {
+            SchedulingPolicy::Fifo => {
+                let control_first = match (self.control.front(), self.data.front()) {
+                    (Some((control, _)), Some((data, _))) => control < data,
+                    (control, _) => control.is_some()
+                };
+                if control_first { self.control.pop_front() } else { self.data.pop_front() }
+            }
+            SchedulingPolicy::StrictPriority => {
+                self.control.pop_front().or_else(|| self.data.pop_front())
+            }
+            SchedulingPolicy::WeightedRoundRobin { control, data } => {
+                self.pop_weighted(control, data)
+            }
+        };
+        item.map(|(_, packet)| packet)
+    }
+
+    /// serves up to `control` control packets, then up to `data` data packets, and so on.
+    /// the turn passes when a class used its weight or has nothing left, a class with weight 0 is only served when the other one is empty
+    fn pop_weighted(&mut self, control: u32, data: u32) -> Option<(u64, Packet)> {
+        //at most: the current class, the other one, and the current one again with a fresh turn
+        for _ in 0..3 {
+            let (queue, weight) = match self.turn {
+                PacketClass::Control => (&mut self.control, control),
+                PacketClass::Data => (&mut self.data, data)
+            };
+            if self.served < weight && !queue.is_empty() {
+                self.served += 1;
+                return queue.pop_front();
+            }
+            self.turn = self.turn.other();
+            self.served = 0;
+        }
+        self.control.pop_front().or_else(|| self.data.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Ack, Fragment};
+    use super::*;
+
+    /// a control packet, told apart by its session id
+    fn control(session_id: u64) -> Packet {
+        Packet {
+            routing_header: SourceRoutingHeader { hop_index: 1, hops: vec![1, 11, 2] },
+            session_id,
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 })
+        }
+    }
+
+    /// a data packet, told apart by its session id
+    fn data(session_id: u64) -> Packet {
+        Packet {
+            routing_header: SourceRoutingHeader { hop_index: 1, hops: vec![1, 11, 2] },
+            session_id,
+            pack_type: PacketType::MsgFragment(Fragment { fragment_index: 0, total_n_fragments: 1, length: 0, data: [0; 128] })
+        }
+    }
+
+    /// pushes the packets, then pops every one of them with the policy, returning their session ids
+    fn serve(packets: Vec<Packet>, policy: SchedulingPolicy) -> Vec<u64> {
+        let mut scheduler = PacketScheduler::default();
+        for packet in packets {
+            scheduler.push(packet);
+        }
+        let served = std::iter::from_fn(|| scheduler.pop(policy)).map(|packet| packet.session_id).collect();
+        assert!(scheduler.is_empty());
+        served
+    }
+
+    #[test]
+    fn fifo_keeps_the_arrival_order() {
+        let packets = vec![data(0), control(1), data(2), control(3)];
+        assert_eq!(serve(packets, SchedulingPolicy::Fifo), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn strict_priority_serves_control_packets_first() {
+        let packets = vec![data(0), control(1), data(2), control(3), data(4)];
+        assert_eq!(serve(packets, SchedulingPolicy::StrictPriority), vec![1, 3, 0, 2, 4]);
+    }
+
+    #[test]
+    fn weighted_round_robin_follows_the_ratio() {
+        let mut packets: Vec<Packet> = (0..6).map(data).collect();
+        packets.extend((10..16).map(control));
+
+        let policy = SchedulingPolicy::WeightedRoundRobin { control: 1, data: 2 };
+        assert_eq!(serve(packets, policy), vec![10, 0, 1, 11, 2, 3, 12, 4, 5, 13, 14, 15]);
+    }
+
+    #[test]
+    fn weighted_round_robin_ratio_holds_while_both_classes_wait() {
+        let mut packets: Vec<Packet> = (0..40).map(data).collect();
+        packets.extend((100..140).map(control));
+
+        let served = serve(packets, SchedulingPolicy::WeightedRoundRobin { control: 3, data: 1 });
+        //3 control packets for every data packet, until the control ones run out
+        let control_served = served[..40].iter().filter(|id| **id >= 100).count();
+        assert_eq!(control_served, 30);
+        assert_eq!(served.len(), 80);
+    }
+
+    #[test]
+    fn weight_zero_class_waits_for_the_other_one() {
+        let packets = vec![data(0), control(1), data(2), control(3)];
+        let policy = SchedulingPolicy::WeightedRoundRobin { control: 1, data: 0 };
+        assert_eq!(serve(packets, policy), vec![1, 3, 0, 2]);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use wg_2024::network::NodeId;
+use wg_2024::packet::NackType;
+use crate::packet_kind::PacketKind;
+
+/// Counters of what a drone did since it was created.
+/// by_kind:             counters of every packet kind.
+/// by_neighbor:         counters of every adjacent node. received, dropped and filtered packets are counted on the node they came from,
+///                      sent and tail-dropped ones on the node they were going to.
+/// nacks:               NACKs created by the drone, by type.
+/// bytes_forwarded:     payload bytes of the MsgFragments sent to adjacent nodes.
+/// flood_requests_seen: FloodRequests received, already visited ones included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DroneStats {
+    pub by_kind: HashMap<PacketKind, PacketCounters>,
+    pub by_neighbor: HashMap<NodeId, PacketCounters>,
+    pub nacks: HashMap<NackKind, u64>,
+    pub bytes_forwarded: u64,
+    pub flood_requests_seen: u64
+}
+
+/// Counters of a packet kind or of an adjacent node.
+/// received:  packets received.
+/// sent:      packets sent to an adjacent node, forwarded ones and the ones created by the drone.
+/// dropped:   packets dropped by the pdr or tail-dropped by a congested link.
+/// filtered:  packets filtered out by the PacketFilter.
+/// shortcuts: packets sent to the controller through ControllerShortcut.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacketCounters {
+    pub received: u64,
+    pub sent: u64,
+    pub dropped: u64,
+    pub filtered: u64,
+    pub shortcuts: u64
+}
+
+/// The type of a [NackType], without the NodeId some types carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NackKind {
+    ErrorInRouting,
+    DestinationIsDrone,
+    Dropped,
+    UnexpectedRecipient
+}
+
+impl NackKind {
+    /// returns the kind of a nack type
+    pub fn of(nack_type: &NackType) -> Self {
+        match nack_type {
+            NackType::ErrorInRouting(_) => NackKind::ErrorInRouting,
+            NackType::DestinationIsDrone => NackKind::DestinationIsDrone,
+            NackType::Dropped => NackKind::Dropped,
+            NackType::UnexpectedRecipient(_) => NackKind::UnexpectedRecipient
+        }
+    }
+}
+
+impl DroneStats {
+    /// returns the counters of a packet kind
+    pub fn kind(&self, kind: PacketKind) -> PacketCounters {
+        self.by_kind.get(&kind).copied().unwrap_or_default()
+    }
+
+    /// returns the counters of an adjacent node
+    pub fn neighbor(&self, id: NodeId) -> PacketCounters {
+        self.by_neighbor.get(&id).copied().unwrap_or_default()
+    }
+
+    /// returns how many NACKs of a type were created
+    pub fn nacks_of(&self, kind: NackKind) -> u64 {
+        self.nacks.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// applies a change to the counters of a kind and, if known, of a neighbor
+    fn count(&mut self, kind: PacketKind, neighbor: Option<NodeId>, change: impl Fn(&mut PacketCounters)) {
+        change(self.by_kind.entry(kind).or_default());
+        if let Some(id) = neighbor {
+            change(self.by_neighbor.entry(id).or_default());
+        }
+    }
+
+    pub(crate) fn received(&mut self, kind: PacketKind, from: Option<NodeId>) {
+        self.count(kind, from, |c| c.received += 1);
+    }
+
+    pub(crate) fn sent(&mut self, kind: PacketKind, to: NodeId, payload_bytes: u64) {
+        self.count(kind, Some(to), |c| c.sent += 1);
+        self.bytes_forwarded += payload_bytes;
+    }
+
+    pub(crate) fn dropped(&mut self, kind: PacketKind, neighbor: Option<NodeId>) {
+        self.count(kind, neighbor, |c| c.dropped += 1);
+    }
+
+    pub(crate) fn filtered(&mut self, kind: PacketKind, from: NodeId) {
+        self.count(kind, Some(from), |c| c.filtered += 1);
+    }
+
+    pub(crate) fn shortcut(&mut self, kind: PacketKind) {
+        self.count(kind, None, |c| c.shortcuts += 1);
+    }
+
+    pub(crate) fn nack(&mut self, nack_type: &NackType) {
+        *self.nacks.entry(NackKind::of(nack_type)).or_default() += 1;
+    }
+}
+
+/// Shared access to the statistics of a drone, which keeps them up to date while running.
+/// Cloning the handle shares the same statistics.
+#[derive(Debug, Clone, Default)]
+pub struct StatsHandle {
+    stats: Arc<Mutex<DroneStats>>
+}
+
+impl StatsHandle {
+    /// returns a copy of the current statistics
+    pub fn snapshot(&self) -> DroneStats {
+        self.lock().clone()
+    }
+
+    /// applies a change to the statistics
+    pub(crate) fn update(&self, change: impl FnOnce(&mut DroneStats)) {
+        change(&mut self.lock());
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DroneStats> {
+        //counters are still meaningful if a thread panicked while holding the lock
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_kept_per_kind() {
+        let mut stats = DroneStats::default();
+        stats.received(PacketKind::MsgFragment, Some(1));
+        stats.received(PacketKind::MsgFragment, Some(1));
+        stats.received(PacketKind::Ack, Some(2));
+        stats.dropped(PacketKind::MsgFragment, Some(1));
+        stats.filtered(PacketKind::Ack, 2);
+        stats.shortcut(PacketKind::Nack);
+
+        assert_eq!(stats.kind(PacketKind::MsgFragment), PacketCounters { received: 2, dropped: 1, ..PacketCounters::default() });
+        assert_eq!(stats.kind(PacketKind::Ack), PacketCounters { received: 1, filtered: 1, ..PacketCounters::default() });
+        assert_eq!(stats.kind(PacketKind::Nack), PacketCounters { shortcuts: 1, ..PacketCounters::default() });
+        assert_eq!(stats.kind(PacketKind::FloodRequest), PacketCounters::default());
+    }
+
+    #[test]
+    fn counters_are_kept_per_neighbor() {
+        let mut stats = DroneStats::default();
+        stats.received(PacketKind::MsgFragment, Some(1));
+        stats.sent(PacketKind::MsgFragment, 2, 128);
+        stats.sent(PacketKind::Ack, 2, 0);
+        stats.dropped(PacketKind::MsgFragment, None);
+        stats.shortcut(PacketKind::Ack);
+
+        assert_eq!(stats.neighbor(1), PacketCounters { received: 1, ..PacketCounters::default() });
+        assert_eq!(stats.neighbor(2), PacketCounters { sent: 2, ..PacketCounters::default() });
+        assert_eq!(stats.neighbor(3), PacketCounters::default());
+        //packets without a neighbor only count for their kind
+        assert_eq!(stats.kind(PacketKind::MsgFragment).dropped, 1);
+        assert_eq!(stats.by_neighbor.len(), 2);
+        assert_eq!(stats.bytes_forwarded, 128);
+    }
+
+    #[test]
+    fn nacks_are_counted_per_type() {
+        let mut stats = DroneStats::default();
+        stats.nack(&NackType::ErrorInRouting(3));
+        stats.nack(&NackType::ErrorInRouting(4));
+        stats.nack(&NackType::Dropped);
+        stats.nack(&NackType::UnexpectedRecipient(11));
+
+        assert_eq!(stats.nacks_of(NackKind::ErrorInRouting), 2);
+        assert_eq!(stats.nacks_of(NackKind::Dropped), 1);
+        assert_eq!(stats.nacks_of(NackKind::UnexpectedRecipient), 1);
+        assert_eq!(stats.nacks_of(NackKind::DestinationIsDrone), 0);
+    }
+
+    #[test]
+    fn cloned_handles_share_the_stats() {
+        let handle = StatsHandle::default();
+        let clone = handle.clone();
+        clone.update(|stats| stats.received(PacketKind::Ack, Some(1)));
+
+        assert_eq!(handle.snapshot().kind(PacketKind::Ack).received, 1);
+    }
+}
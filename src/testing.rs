@@ -0,0 +1,441 @@
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use crate::drone::RustableDrone;
+
+pub mod conformance;
+
+/// How long `recv` and `recv_event` wait before giving up
+pub const RECV_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long `shutdown` waits for the drones to stop
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Builds a network of drones running in their own threads, with fake clients and servers driven by the test.
+/// Every node gets a crossbeam channel, and every edge gives both ends a sender to the other one.
+/// Drones are created through [Drone::new], so any implementation can be tested, [RustableDrone] being the default one.
+///
+/// ```ignore
+/// let topology = TopologyBuilder::new()
+///     .client(1)
+///     .drone(11, 0.0)
+///     .drone(12, 0.0)
+///     .server(2)
+///     .edges([(1, 11), (11, 12), (12, 2)])
+///     .build();
+/// topology.node(1).send(fragment(&[1, 11, 12, 2], 0, 0));
+/// assert!(topology.node(2).recv().is_some());
+/// ```
+pub struct TopologyBuilder<D = RustableDrone> {
+    drones: Vec<(NodeId, f32, Configure<D>)>,
+    nodes: Vec<(NodeId, NodeType)>,
+    edges: Vec<(NodeId, NodeId)>,
+    _drone: PhantomData<fn() -> D>
+}
+
+/// Changes a drone after its creation, before it starts running
+type Configure<D> = Box<dyn FnOnce(D) -> D>;
+
+impl TopologyBuilder<RustableDrone> {
+    /// creates an empty topology of [RustableDrone]s, use `default` for other implementations
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<D> Default for TopologyBuilder<D> {
+    fn default() -> Self {
+        TopologyBuilder {
+            drones: Vec::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            _drone: PhantomData
+        }
+    }
+}
+
+impl<D: Drone + Send + 'static> TopologyBuilder<D> {
+    /// adds a drone with a packet drop rate
+    pub fn drone(self, id: NodeId, pdr: f32) -> Self {
+        self.drone_with(id, pdr, |drone| drone)
+    }
+
+    /// adds a drone with a packet drop rate, changed by `configure` before it starts running.
+    /// eg: `|mut drone: RustableDrone| { drone.settings.filter_packets = false; drone }`
+    pub fn drone_with(mut self, id: NodeId, pdr: f32, configure: impl FnOnce(D) -> D + 'static) -> Self {
+        self.drones.push((id, pdr, Box::new(configure)));
+        self
+    }
+
+    /// adds a fake client
+    pub fn client(mut self, id: NodeId) -> Self {
+        self.nodes.push((id, NodeType::Client));
+        self
+    }
+
+    /// adds a fake server
+    pub fn server(mut self, id: NodeId) -> Self {
+        self.nodes.push((id, NodeType::Server));
+        self
+    }
+
+    /// connects two nodes in both directions
+    pub fn edge(mut self, a: NodeId, b: NodeId) -> Self {
+        self.edges.push((a, b));
+        self
+    }
+
+    /// connects every pair of nodes in both directions
+    pub fn edges(mut self, edges: impl IntoIterator<Item = (NodeId, NodeId)>) -> Self {
+        self.edges.extend(edges);
+        self
+    }
+
+    /// creates the channels, then creates and starts every drone
+    ///
+    /// # Panics
+    /// if two nodes have the same id, an edge connects a node to itself or to an unknown node, or a thread can't be spawned
+    pub fn build(self) -> Topology {
+        let ids: Vec<NodeId> = self.drones.iter().map(|(id, _, _)| *id).chain(self.nodes.iter().map(|(id, _)| *id)).collect();
+        let mut channels: HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)> = HashMap::new();
+        for id in &ids {
+            if channels.insert(*id, unbounded()).is_some() {
+                panic!("node {} is added more than once", id);
+            }
+        }
+
+        let mut neighbors: HashMap<NodeId, BTreeSet<NodeId>> = ids.iter().map(|id| (*id, BTreeSet::new())).collect();
+        for (a, b) in self.edges {
+            if a == b || !channels.contains_key(&a) || !channels.contains_key(&b) {
+                panic!("invalid edge ({}, {})", a, b);
+            }
+            neighbors.entry(a).or_default().insert(b);
+            neighbors.entry(b).or_default().insert(a);
+        }
+        let senders_to = |id: NodeId| -> HashMap<NodeId, Sender<Packet>> {
+            neighbors[&id].iter().map(|n| (*n, channels[n].0.clone())).collect()
+        };
+
+        let mut drones = HashMap::new();
+        for (id, pdr, configure) in self.drones {
+            let (event_send, events) = unbounded();
+            let (commands, command_recv) = unbounded();
+            let drone = configure(D::new(id, event_send, command_recv, channels[&id].1.clone(), senders_to(id), pdr));
+            let thread = thread::Builder::new()
+                .name(format!("drone {}", id))
+                .spawn(move || {
+                    let mut drone = drone;
+                    drone.run();
+                })
+                .unwrap_or_else(|error| panic!("could not spawn drone {}: {}", id, error));
+
+            drones.insert(id, DroneHandle {
+                id,
+                commands,
+                events,
+                packets: channels[&id].0.clone(),
+                thread: Some(thread)
+            });
+        }
+
+        let mut nodes = HashMap::new();
+        for (id, node_type) in self.nodes {
+            nodes.insert(id, NodeHandle {
+                id,
+                node_type,
+                packet_recv: channels[&id].1.clone(),
+                packet_send: channels[&id].0.clone(),
+                neighbors: senders_to(id)
+            });
+        }
+
+        Topology {
+            drones,
+            nodes,
+            neighbors
+        }
+    }
+}
+
+/// A running network built by a [TopologyBuilder].
+/// Dropping it crashes every drone and removes their senders, without waiting for them to stop.
+pub struct Topology {
+    drones: HashMap<NodeId, DroneHandle>,
+    nodes: HashMap<NodeId, NodeHandle>,
+    neighbors: HashMap<NodeId, BTreeSet<NodeId>>
+}
+
+impl Topology {
+    /// returns the handle of a drone
+    ///
+    /// # Panics
+    /// if there is no drone with that id
+    pub fn drone(&self, id: NodeId) -> &DroneHandle {
+        self.drones.get(&id).unwrap_or_else(|| panic!("there is no drone {}", id))
+    }
+
+    /// returns the handle of a client or server
+    ///
+    /// # Panics
+    /// if there is no client or server with that id
+    pub fn node(&self, id: NodeId) -> &NodeHandle {
+        self.nodes.get(&id).unwrap_or_else(|| panic!("there is no client or server {}", id))
+    }
+
+    /// returns the nodes connected to a node
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.neighbors.get(&id).into_iter().flatten().copied()
+    }
+
+    /// crashes a drone like the simulation controller would:
+    /// sends it the Crash command, then removes it from its neighbors and its neighbors from it
+    pub fn crash(&mut self, id: NodeId) {
+        let neighbors: Vec<NodeId> = self.neighbors.remove(&id).into_iter().flatten().collect();
+        self.drone(id).command(DroneCommand::Crash);
+        for neighbor in neighbors {
+            self.drone(id).command(DroneCommand::RemoveSender(neighbor));
+            if let Some(drone) = self.drones.get(&neighbor) {
+                drone.command(DroneCommand::RemoveSender(id));
+            }
+            if let Some(node) = self.nodes.get_mut(&neighbor) {
+                node.neighbors.remove(&id);
+            }
+            if let Some(neighbors) = self.neighbors.get_mut(&neighbor) {
+                neighbors.remove(&id);
+            }
+        }
+    }
+
+    /// crashes every drone, waits up to [SHUTDOWN_TIMEOUT] for their threads to end
+    /// and returns the ids of those that panicked or are still running
+    pub fn shutdown(mut self) -> Shutdown {
+        self.stop();
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        let mut report = Shutdown::default();
+        for drone in self.drones.values_mut() {
+            while !drone.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(5));
+            }
+            let Some(thread) = drone.thread.take() else { continue };
+            if !thread.is_finished() {
+                //a drone whose run() never returns can't be stopped, its thread is left behind
+                report.stuck.push(drone.id);
+            } else if thread.join().is_err() {
+                report.panicked.push(drone.id);
+            }
+        }
+        report.panicked.sort();
+        report.stuck.sort();
+        report
+    }
+
+    /// crashes every drone, removes every sender and disconnects the channels held by the topology
+    fn stop(&mut self) {
+        for drone in self.drones.values() {
+            drone.command(DroneCommand::Crash);
+            for neighbor in self.neighbors.get(&drone.id).into_iter().flatten() {
+                drone.command(DroneCommand::RemoveSender(*neighbor));
+            }
+        }
+        for node in self.nodes.values_mut() {
+            node.neighbors.clear();
+        }
+        for drone in self.drones.values_mut() {
+            //a closed channel, so that the drone's packet channel can disconnect
+            drone.packets = unbounded().0;
+        }
+    }
+}
+
+impl Drop for Topology {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The drones that did not stop cleanly in [Topology::shutdown]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Shutdown {
+    /// the drones whose thread panicked
+    pub panicked: Vec<NodeId>,
+    /// the drones still running when the timeout expired
+    pub stuck: Vec<NodeId>
+}
+
+impl Shutdown {
+    /// returns true if every drone stopped without panicking
+    pub fn is_clean(&self) -> bool {
+        self.panicked.is_empty() && self.stuck.is_empty()
+    }
+}
+
+/// The controller's side of a running drone
+pub struct DroneHandle {
+    id: NodeId,
+    commands: Sender<DroneCommand>,
+    events: Receiver<DroneEvent>,
+    packets: Sender<Packet>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl DroneHandle {
+    /// returns the id of the drone
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// sends a command to the drone, a drone that stopped listening is ignored
+    pub fn command(&self, command: DroneCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// puts a packet in the drone's channel, as if an adjacent node sent it
+    pub fn inject(&self, packet: Packet) {
+        let _ = self.packets.send(packet);
+    }
+
+    /// returns a sender to the drone's channel, eg: to give it back to a neighbor through `AddSender`
+    pub fn sender(&self) -> Sender<Packet> {
+        self.packets.clone()
+    }
+
+    /// waits up to [RECV_TIMEOUT] for the next event sent to the controller
+    pub fn recv_event(&self) -> Option<DroneEvent> {
+        self.recv_event_timeout(RECV_TIMEOUT)
+    }
+
+    /// waits up to `timeout` for the next event sent to the controller
+    pub fn recv_event_timeout(&self, timeout: Duration) -> Option<DroneEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// returns every event sent to the controller that was not received yet
+    pub fn drain_events(&self) -> Vec<DroneEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// returns true if the drone's thread has ended
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+}
+
+/// A fake client or server, whose packets are sent and received by the test
+pub struct NodeHandle {
+    id: NodeId,
+    node_type: NodeType,
+    packet_recv: Receiver<Packet>,
+    packet_send: Sender<Packet>,
+    neighbors: HashMap<NodeId, Sender<Packet>>
+}
+
+impl NodeHandle {
+    /// returns the id of the node
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// returns whether the node is a client or a server
+    pub fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+
+    /// sends a packet to the current hop of its routing header, returns false if it is not a neighbor
+    pub fn send(&self, packet: Packet) -> bool {
+        match packet.routing_header.current_hop() {
+            Some(to) => self.send_to(to, packet),
+            None => false
+        }
+    }
+
+    /// sends a packet to a neighbor, returns false if it is not a neighbor
+    pub fn send_to(&self, to: NodeId, packet: Packet) -> bool {
+        self.neighbors.get(&to).is_some_and(|sender| sender.send(packet).is_ok())
+    }
+
+    /// starts a flood: sends a FloodRequest with the node in its path trace to every neighbor
+    pub fn flood(&self, flood_id: u64, session_id: u64) {
+        for to in self.neighbors.keys() {
+            self.send_to(*to, flood_request(flood_id, session_id, vec![(self.id, self.node_type)]));
+        }
+    }
+
+    /// waits up to [RECV_TIMEOUT] for the next packet
+    pub fn recv(&self) -> Option<Packet> {
+        self.recv_timeout(RECV_TIMEOUT)
+    }
+
+    /// waits up to `timeout` for the next packet
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Packet> {
+        self.packet_recv.recv_timeout(timeout).ok()
+    }
+
+    /// returns every packet received and not taken yet
+    pub fn drain(&self) -> Vec<Packet> {
+        self.packet_recv.try_iter().collect()
+    }
+
+    /// returns a sender to the node's own channel, to deliver packets as if they came from the network
+    pub fn sender(&self) -> Sender<Packet> {
+        self.packet_send.clone()
+    }
+}
+
+/// creates a MsgFragment travelling along `hops`, leaving the first one
+pub fn fragment(hops: &[NodeId], session_id: u64, fragment_index: u64) -> Packet {
+    let mut data = [0; 128];
+    data[0] = fragment_index as u8;
+    Packet {
+        routing_header: SourceRoutingHeader { hop_index: 1, hops: hops.to_vec() },
+        session_id,
+        pack_type: PacketType::MsgFragment(Fragment {
+            fragment_index,
+            total_n_fragments: fragment_index + 1,
+            length: 1,
+            data
+        })
+    }
+}
+
+/// creates an Ack travelling along `hops`, leaving the first one
+pub fn ack(hops: &[NodeId], session_id: u64, fragment_index: u64) -> Packet {
+    Packet {
+        routing_header: SourceRoutingHeader { hop_index: 1, hops: hops.to_vec() },
+        session_id,
+        pack_type: PacketType::Ack(Ack { fragment_index })
+    }
+}
+
+/// creates a Nack travelling along `hops`, leaving the first one
+pub fn nack(hops: &[NodeId], session_id: u64, fragment_index: u64, nack_type: NackType) -> Packet {
+    Packet {
+        routing_header: SourceRoutingHeader { hop_index: 1, hops: hops.to_vec() },
+        session_id,
+        pack_type: PacketType::Nack(Nack { fragment_index, nack_type })
+    }
+}
+
+/// creates a FloodRequest with a path trace, started by its first node
+pub fn flood_request(flood_id: u64, session_id: u64, path_trace: Vec<(NodeId, NodeType)>) -> Packet {
+    let initiator_id = path_trace.first().map_or(0, |(id, _)| *id);
+    Packet {
+        routing_header: SourceRoutingHeader { hop_index: 0, hops: Vec::new() },
+        session_id,
+        pack_type: PacketType::FloodRequest(FloodRequest { flood_id, initiator_id, path_trace })
+    }
+}
+
+/// creates a FloodResponse travelling along `hops`, leaving the first one
+pub fn flood_response(hops: &[NodeId], session_id: u64, flood_id: u64, path_trace: Vec<(NodeId, NodeType)>) -> Packet {
+    Packet {
+        routing_header: SourceRoutingHeader { hop_index: 1, hops: hops.to_vec() },
+        session_id,
+        pack_type: PacketType::FloodResponse(FloodResponse { flood_id, path_trace })
+    }
+}
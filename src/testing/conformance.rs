@@ -0,0 +1,363 @@
+//! Checks of the wg_2024 protocol rules, usable with any drone implementation.
+//! Every check builds its own network, sends packets through it and panics if the drone does not behave as the protocol states.
+//! Most checks use the chain client 1 -> drone 11 -> drone 12 -> server 2.
+//!
+//! ```ignore
+//! #[test]
+//! fn my_drone_follows_the_protocol() {
+//!     rustable_drone::testing::conformance::run_all::<MyDrone>();
+//! }
+//! ```
+
+use std::panic;
+use std::thread;
+use std::time::{Duration, Instant};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{NackType, NodeType, Packet, PacketType};
+use super::{ack, flood_response, fragment, nack, DroneHandle, NodeHandle, Topology, TopologyBuilder, RECV_TIMEOUT};
+
+/// How long a check waits to be sure that nothing arrives
+const QUIET: Duration = Duration::from_millis(100);
+
+/// A named check, generic over the drone implementation
+pub type Check = (&'static str, fn());
+
+/// returns every check, with its name
+pub fn checks<D: Drone + Send + 'static>() -> Vec<Check> {
+    vec![
+        ("forwards_fragment", forwards_fragment::<D>),
+        ("nacks_unexpected_recipient", nacks_unexpected_recipient::<D>),
+        ("nacks_destination_is_drone", nacks_destination_is_drone::<D>),
+        ("nacks_error_in_routing", nacks_error_in_routing::<D>),
+        ("nacks_dropped", nacks_dropped::<D>),
+        ("shortcuts_nack_to_removed_sender", shortcuts_nack_to_removed_sender::<D>),
+        ("forwards_ack_and_nack", forwards_ack_and_nack::<D>),
+        ("shortcuts_undeliverable_ack_and_nack", shortcuts_undeliverable_ack_and_nack::<D>),
+        ("shortcuts_only_deliverable_ack_and_nack", shortcuts_only_deliverable_ack_and_nack::<D>),
+        ("forwards_flood_request", forwards_flood_request::<D>),
+        ("answers_visited_flood_request", answers_visited_flood_request::<D>),
+        ("answers_flood_request_without_other_neighbors", answers_flood_request_without_other_neighbors::<D>),
+        ("forwards_flood_response", forwards_flood_response::<D>),
+        ("shortcuts_undeliverable_flood_response", shortcuts_undeliverable_flood_response::<D>),
+        ("crashing_drone", crashing_drone::<D>),
+        ("follows_commands", follows_commands::<D>)
+    ]
+}
+
+/// runs every check, then panics with the names of those that failed
+pub fn run_all<D: Drone + Send + 'static>() {
+    let failed: Vec<&str> = checks::<D>().into_iter()
+        .filter(|(_, check)| panic::catch_unwind(check).is_err())
+        .map(|(name, _)| name)
+        .collect();
+    assert!(failed.is_empty(), "failed conformance checks: {}", failed.join(", "));
+}
+
+/// a fragment is forwarded with the hop_index increased, and the controller is notified
+pub fn forwards_fragment<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    let sent = fragment(&[1, 11, 12, 2], 5, 0);
+    topology.node(1).send(sent.clone());
+
+    let packet = topology.node(2).recv().expect("the server should receive the fragment");
+    assert_eq!(packet.routing_header, header(&[1, 11, 12, 2], 3));
+    assert_eq!(packet.session_id, 5);
+    assert_eq!(packet.pack_type, sent.pack_type);
+
+    let event = expect_event(topology.drone(11), |event| matches!(event, DroneEvent::PacketSent(_)));
+    let DroneEvent::PacketSent(packet) = event else { unreachable!() };
+    assert_eq!(packet.routing_header, header(&[1, 11, 12, 2], 2));
+    finish(topology);
+}
+
+/// a fragment whose current hop is not the drone is answered with UnexpectedRecipient(drone), on the path back from the drone
+pub fn nacks_unexpected_recipient<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    topology.node(1).send_to(11, fragment(&[1, 13, 12, 2], 5, 4));
+
+    expect_nack(topology.node(1), 5, 4, NackType::UnexpectedRecipient(11), header(&[11, 1], 1));
+    assert!(topology.node(2).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// a fragment whose last hop is the drone is answered with DestinationIsDrone
+pub fn nacks_destination_is_drone<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    topology.node(1).send(fragment(&[1, 11], 5, 4));
+
+    expect_nack(topology.node(1), 5, 4, NackType::DestinationIsDrone, header(&[11, 1], 1));
+    finish(topology);
+}
+
+/// a fragment whose next hop is not a neighbor is answered with ErrorInRouting(next hop), the NACK travelling back through the other drones
+pub fn nacks_error_in_routing<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    topology.node(1).send(fragment(&[1, 11, 13, 2], 5, 4));
+    expect_nack(topology.node(1), 5, 4, NackType::ErrorInRouting(13), header(&[11, 1], 1));
+
+    topology.node(1).send(fragment(&[1, 11, 12, 13], 6, 2));
+    expect_nack(topology.node(1), 6, 2, NackType::ErrorInRouting(13), header(&[12, 11, 1], 2));
+    assert!(topology.node(2).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// a fragment dropped by the pdr is answered with Dropped, and the controller receives a PacketDropped event
+pub fn nacks_dropped<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(1.0, 0.0);
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 5, 4));
+
+    expect_nack(topology.node(1), 5, 4, NackType::Dropped, header(&[11, 1], 1));
+    let event = expect_event(topology.drone(11), |event| matches!(event, DroneEvent::PacketDropped(_)));
+    let DroneEvent::PacketDropped(packet) = event else { unreachable!() };
+    assert!(matches!(packet.pack_type, PacketType::MsgFragment(_)));
+    assert_eq!(packet.session_id, 5);
+    assert!(topology.node(2).recv_timeout(QUIET).is_none());
+    finish(topology);
+
+    let topology = chain::<D>(0.0, 1.0);
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 5, 4));
+    expect_nack(topology.node(1), 5, 4, NackType::Dropped, header(&[12, 11, 1], 2));
+    finish(topology);
+}
+
+/// a NACK to a sender the drone has no channel to anymore is delivered by the controller
+pub fn shortcuts_nack_to_removed_sender<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    command(topology.drone(11), DroneCommand::RemoveSender(1));
+    topology.node(1).send(fragment(&[1, 11, 13, 2], 5, 4));
+
+    let event = expect_event(topology.drone(11), |event| matches!(event, DroneEvent::ControllerShortcut(_)));
+    let DroneEvent::ControllerShortcut(packet) = event else { unreachable!() };
+    assert_eq!(packet, Packet {
+        routing_header: header(&[11, 1], 1),
+        session_id: 5,
+        pack_type: nack(&[], 5, 4, NackType::ErrorInRouting(13)).pack_type
+    });
+    assert!(topology.node(1).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// acks and nacks are forwarded like fragments, and are never dropped
+pub fn forwards_ack_and_nack<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(1.0, 1.0);
+
+    topology.node(2).send(ack(&[2, 12, 11, 1], 5, 4));
+    let packet = topology.node(1).recv().expect("the client should receive the ACK");
+    assert_eq!(packet.routing_header, header(&[2, 12, 11, 1], 3));
+    assert_eq!(packet.pack_type, ack(&[], 5, 4).pack_type);
+
+    topology.node(2).send(nack(&[2, 12, 11, 1], 5, 4, NackType::Dropped));
+    let packet = topology.node(1).recv().expect("the client should receive the NACK");
+    assert_eq!(packet.routing_header, header(&[2, 12, 11, 1], 3));
+    assert_eq!(packet.pack_type, nack(&[], 5, 4, NackType::Dropped).pack_type);
+    finish(topology);
+}
+
+/// acks and nacks that can't be forwarded are sent to the controller through ControllerShortcut, as if forwarded to the next hop
+pub fn shortcuts_undeliverable_ack_and_nack<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+
+    topology.node(2).send(ack(&[2, 12, 13, 1], 5, 4));
+    let event = expect_event(topology.drone(12), |event| matches!(event, DroneEvent::ControllerShortcut(_)));
+    let DroneEvent::ControllerShortcut(packet) = event else { unreachable!() };
+    assert_eq!(packet.routing_header, header(&[2, 12, 13, 1], 2));
+    assert_eq!(packet.pack_type, ack(&[], 5, 4).pack_type);
+
+    topology.node(2).send(nack(&[2, 12, 13, 1], 5, 4, NackType::Dropped));
+    let event = expect_event(topology.drone(12), |event| matches!(event, DroneEvent::ControllerShortcut(_)));
+    let DroneEvent::ControllerShortcut(packet) = event else { unreachable!() };
+    assert_eq!(packet.routing_header, header(&[2, 12, 13, 1], 2));
+    assert_eq!(packet.pack_type, nack(&[], 5, 4, NackType::Dropped).pack_type);
+
+    assert!(topology.node(1).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// acks and nacks are never sent through ControllerShortcut with a route the controller can't follow:
+/// past its last hop, or back to the drone when it is their destination
+pub fn shortcuts_only_deliverable_ack_and_nack<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+
+    //the drone is the destination
+    topology.node(2).send(ack(&[2, 12], 5, 4));
+    topology.node(2).send(nack(&[2, 12], 5, 4, NackType::Dropped));
+    //the intended recipient is the last hop, but it is not the drone
+    topology.node(2).send_to(12, ack(&[2, 13], 5, 4));
+    thread::sleep(QUIET);
+
+    for event in topology.drone(12).drain_events() {
+        if let DroneEvent::ControllerShortcut(packet) = event {
+            let current_hop = packet.routing_header.current_hop();
+            assert!(current_hop.is_some_and(|hop| hop != 12), "drone 12 sent an undeliverable packet through the controller: {:?}", packet);
+        }
+    }
+    finish(topology);
+}
+
+/// a FloodRequest is forwarded to every neighbor but the sender, with the drone added to its path trace
+pub fn forwards_flood_request<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(1.0, 1.0);
+    topology.node(1).flood(7, 5);
+
+    let packet = topology.node(2).recv().expect("the server should receive the FloodRequest");
+    let PacketType::FloodRequest(request) = packet.pack_type else { panic!("expected a FloodRequest, got {:?}", packet.pack_type) };
+    assert_eq!(request.flood_id, 7);
+    assert_eq!(request.initiator_id, 1);
+    assert_eq!(request.path_trace, vec![(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone)]);
+    assert!(topology.node(1).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// a FloodRequest already seen is answered with a FloodResponse, on the path back from the drone
+pub fn answers_visited_flood_request<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    topology.node(1).flood(7, 5);
+    assert!(topology.node(2).recv().is_some(), "the server should receive the FloodRequest");
+    topology.node(1).flood(7, 5);
+
+    expect_flood_response(topology.node(1), 7, vec![(1, NodeType::Client), (11, NodeType::Drone)], header(&[11, 1], 1));
+    assert!(topology.node(2).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// a FloodRequest coming from the only neighbor is answered with a FloodResponse
+pub fn answers_flood_request_without_other_neighbors<D: Drone + Send + 'static>() {
+    let topology = TopologyBuilder::<D>::default()
+        .client(1)
+        .drone(11, 0.0)
+        .edge(1, 11)
+        .build();
+    topology.node(1).flood(7, 5);
+
+    expect_flood_response(topology.node(1), 7, vec![(1, NodeType::Client), (11, NodeType::Drone)], header(&[11, 1], 1));
+    finish(topology);
+}
+
+/// a FloodResponse is forwarded like a fragment, and is never dropped
+pub fn forwards_flood_response<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(1.0, 1.0);
+    let path_trace = vec![(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (2, NodeType::Server)];
+    topology.node(2).send(flood_response(&[2, 12, 11, 1], 5, 7, path_trace.clone()));
+
+    expect_flood_response(topology.node(1), 7, path_trace, header(&[2, 12, 11, 1], 3));
+    finish(topology);
+}
+
+/// FloodResponses that can't be forwarded are sent to the controller through ControllerShortcut
+pub fn shortcuts_undeliverable_flood_response<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+    let path_trace = vec![(1, NodeType::Client), (13, NodeType::Drone), (12, NodeType::Drone), (2, NodeType::Server)];
+    topology.node(2).send(flood_response(&[2, 12, 13, 1], 5, 7, path_trace.clone()));
+
+    let event = expect_event(topology.drone(12), |event| matches!(event, DroneEvent::ControllerShortcut(_)));
+    let DroneEvent::ControllerShortcut(packet) = event else { unreachable!() };
+    assert_eq!(packet.routing_header.hops, vec![2, 12, 13, 1]);
+    assert_eq!(packet.pack_type, flood_response(&[], 5, 7, path_trace).pack_type);
+
+    assert!(topology.node(1).recv_timeout(QUIET).is_none());
+    finish(topology);
+}
+
+/// a crashing drone answers fragments with ErrorInRouting(drone), keeps forwarding acks and drops FloodRequests,
+/// then stops once every sender has been removed
+pub fn crashing_drone<D: Drone + Send + 'static>() {
+    let mut topology = chain::<D>(0.0, 0.0);
+    command(topology.drone(11), DroneCommand::Crash);
+
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 5, 4));
+    expect_nack(topology.node(1), 5, 4, NackType::ErrorInRouting(11), header(&[11, 1], 1));
+
+    topology.node(2).send(ack(&[2, 12, 11, 1], 5, 4));
+    let packet = topology.node(1).recv().expect("the client should receive the ACK");
+    assert_eq!(packet.routing_header, header(&[2, 12, 11, 1], 3));
+
+    topology.node(1).flood(7, 5);
+    assert!(topology.node(2).recv_timeout(QUIET).is_none());
+
+    //removes every sender of the drone, and the drone from its neighbors
+    topology.crash(11);
+    let deadline = Instant::now() + RECV_TIMEOUT;
+    while !topology.drone(11).is_finished() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert!(topology.drone(11).is_finished(), "drone 11 should stop once every sender has been removed");
+    finish(topology);
+}
+
+/// SetPacketDropRate, RemoveSender and AddSender change how fragments are handled
+pub fn follows_commands<D: Drone + Send + 'static>() {
+    let topology = chain::<D>(0.0, 0.0);
+
+    command(topology.drone(11), DroneCommand::RemoveSender(12));
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 5, 0));
+    expect_nack(topology.node(1), 5, 0, NackType::ErrorInRouting(12), header(&[11, 1], 1));
+
+    command(topology.drone(11), DroneCommand::AddSender(12, topology.drone(12).sender()));
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 5, 1));
+    assert!(topology.node(2).recv().is_some(), "the server should receive the fragment");
+
+    command(topology.drone(11), DroneCommand::SetPacketDropRate(1.0));
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 5, 2));
+    expect_nack(topology.node(1), 5, 2, NackType::Dropped, header(&[11, 1], 1));
+    finish(topology);
+}
+
+/// builds the chain client 1 -> drone 11 -> drone 12 -> server 2
+fn chain<D: Drone + Send + 'static>(pdr_11: f32, pdr_12: f32) -> Topology {
+    TopologyBuilder::<D>::default()
+        .client(1)
+        .drone(11, pdr_11)
+        .drone(12, pdr_12)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build()
+}
+
+fn header(hops: &[NodeId], hop_index: usize) -> SourceRoutingHeader {
+    SourceRoutingHeader { hop_index, hops: hops.to_vec() }
+}
+
+/// sends a command, then gives the drone time to execute it before any packet is sent
+fn command(drone: &DroneHandle, command: DroneCommand) {
+    drone.command(command);
+    thread::sleep(QUIET);
+}
+
+/// stops the network, checking that every drone stopped without panicking
+fn finish(topology: Topology) {
+    let report = topology.shutdown();
+    assert!(report.is_clean(), "drones {:?} panicked and drones {:?} did not stop", report.panicked, report.stuck);
+}
+
+/// waits for an event matching a condition, skipping the others
+fn expect_event(drone: &DroneHandle, condition: impl Fn(&DroneEvent) -> bool) -> DroneEvent {
+    let deadline = Instant::now() + RECV_TIMEOUT;
+    while let Some(event) = drone.recv_event_timeout(deadline.saturating_duration_since(Instant::now())) {
+        if condition(&event) {
+            return event;
+        }
+    }
+    panic!("drone {} did not send the expected event", drone.id());
+}
+
+/// waits for a NACK and checks every field of it
+fn expect_nack(node: &NodeHandle, session_id: u64, fragment_index: u64, nack_type: NackType, routing_header: SourceRoutingHeader) {
+    let packet = node.recv().unwrap_or_else(|| panic!("node {} should receive a {:?} NACK", node.id(), nack_type));
+    assert_eq!(packet, Packet {
+        routing_header,
+        session_id,
+        pack_type: nack(&[], session_id, fragment_index, nack_type).pack_type
+    });
+}
+
+/// waits for a FloodResponse and checks every field of it
+fn expect_flood_response(node: &NodeHandle, flood_id: u64, path_trace: Vec<(NodeId, NodeType)>, routing_header: SourceRoutingHeader) {
+    let packet = node.recv().unwrap_or_else(|| panic!("node {} should receive a FloodResponse", node.id()));
+    let PacketType::FloodResponse(response) = packet.pack_type else { panic!("expected a FloodResponse, got {:?}", packet.pack_type) };
+    assert_eq!(response.flood_id, flood_id);
+    assert_eq!(response.path_trace, path_trace);
+    assert_eq!(packet.routing_header, routing_header);
+}
+
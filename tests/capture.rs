@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use crossbeam_channel::unbounded;
+use rustable_drone::testing::{flood_request, fragment, RECV_TIMEOUT};
+use rustable_drone::{replay_capture, CaptureRecord, CaptureSink, CapturedPacket, CapturedPacketType, Decision, Direction, DroneSettings, LatencyDistribution, RustableDroneBuilder};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NodeType, Packet, PacketType};
+
+const FRAGMENTS: u64 = 40;
+
+/// what the drone decided for every packet, without the times
+fn decisions(records: &[CaptureRecord]) -> Vec<(Direction, Option<NodeId>, Decision, CapturedPacket)> {
+    records.iter()
+        .map(|record| (record.direction, record.neighbor, record.decision, record.packet.clone()))
+        .collect()
+}
+
+#[test]
+fn replayed_capture_takes_the_same_decisions() {
+    let (client_send, client_recv) = unbounded();
+    let (server_send, _server_recv) = unbounded();
+    let (packet_send, packet_recv) = unbounded();
+    let (controller_send, controller_events) = unbounded();
+    let (commands, controller_recv) = unbounded();
+    let (capture_send, capture_recv) = unbounded();
+
+    let mut drone = RustableDroneBuilder::new(11, controller_send, controller_recv, packet_recv, HashMap::from([(1, client_send), (2, server_send)]))
+        .pdr(0.5)
+        .seed(42)
+        .capture(CaptureSink::channel(capture_send))
+        .build()
+        .unwrap();
+    let thread = thread::spawn(move || drone.run());
+
+    for fragment_index in 0..FRAGMENTS {
+        packet_send.send(fragment(&[1, 11, 2], 0, fragment_index)).unwrap();
+        thread::sleep(Duration::from_millis(2));
+    }
+
+    //every fragment is either forwarded to the server or nacked back to the client
+    let mut handled = 0;
+    while handled < FRAGMENTS {
+        match controller_events.recv_timeout(RECV_TIMEOUT).expect("the drone should handle every fragment") {
+            DroneEvent::PacketSent(packet) if matches!(packet.pack_type, PacketType::MsgFragment(_)) => handled += 1,
+            DroneEvent::PacketDropped(_) => handled += 1,
+            _ => {}
+        }
+    }
+    let nacked = client_recv.try_iter().count();
+    assert!(nacked > 0 && nacked < FRAGMENTS as usize, "the pdr should drop some fragments, not all of them");
+
+    commands.send(DroneCommand::Crash).unwrap();
+    commands.send(DroneCommand::RemoveSender(1)).unwrap();
+    commands.send(DroneCommand::RemoveSender(2)).unwrap();
+    thread.join().unwrap();
+    let captured: Vec<CaptureRecord> = capture_recv.try_iter().collect();
+
+    let replayed = replay_capture(11, captured.clone(), |builder| builder.pdr(0.5).seed(42)).unwrap();
+    assert_eq!(decisions(&replayed), decisions(&captured));
+
+    //another seed drops other fragments
+    let reseeded = replay_capture(11, captured.clone(), |builder| builder.pdr(0.5).seed(7)).unwrap();
+    assert_ne!(decisions(&reseeded), decisions(&captured));
+}
+
+/// a record of a packet received from `neighbor` at `at` milliseconds
+fn received(at: u64, neighbor: NodeId, packet: &Packet) -> CaptureRecord {
+    CaptureRecord {
+        at: Duration::from_millis(at),
+        direction: Direction::Incoming,
+        neighbor: Some(neighbor),
+        decision: Decision::Received,
+        packet: packet.into()
+    }
+}
+
+#[test]
+fn flooding_with_random_latency_is_reproducible() {
+    //floods from node 1 reach neighbors 2..=6, whose fragments to node 1 are dropped with the pdr.
+    //packets are far enough apart that whatever they cause is released before the next ones arrive
+    let mut records = Vec::new();
+    for flood_id in 0..10 {
+        records.push(received(flood_id * 50, 1, &flood_request(flood_id, 0, vec![(1, NodeType::Client)])));
+        for neighbor in 2..=6 {
+            records.push(received(flood_id * 50 + 25, neighbor, &fragment(&[neighbor, 11, 1], 0, flood_id)));
+        }
+    }
+    let configure = |builder: RustableDroneBuilder| {
+        let settings = DroneSettings {
+            latency: LatencyDistribution::Uniform { min: Duration::ZERO, max: Duration::from_millis(20) },
+            ..DroneSettings::default()
+        };
+        builder.pdr(0.5).seed(42).settings(settings)
+    };
+
+    let first = replay_capture(11, records.clone(), configure).unwrap();
+    let second = replay_capture(11, records, configure).unwrap();
+    let flooded = first.iter()
+        .filter(|record| record.direction == Direction::Outgoing && matches!(record.packet.pack_type, CapturedPacketType::FloodRequest { .. }))
+        .count();
+    assert_eq!(flooded, 10 * 5, "every flood should be forwarded to the other 5 neighbors");
+    assert_eq!(decisions(&first), decisions(&second));
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+use rustable_drone::config::{load_profiles, profiles_from_json, profiles_from_toml, ConfigError};
+use rustable_drone::{FaultAction, LatencyDistribution, PacketKind};
+
+/// the TOML example of the README, which must keep loading
+fn readme_example() -> &'static str {
+    let readme = include_str!("../README.md");
+    let section = &readme[readme.find("## Configuration files").expect("the README should have a configuration section")..];
+    let start = section.find("```toml\n").expect("the section should have a TOML example") + "```toml\n".len();
+    let end = start + section[start..].find("```").expect("the TOML example should be closed");
+    &section[start..end]
+}
+
+#[test]
+fn loads_the_readme_example() {
+    let profiles = profiles_from_toml(readme_example()).expect("the README example should be valid");
+    assert_eq!(profiles.len(), 1);
+
+    let profile = &profiles[&3];
+    assert!(profile.settings.log_to_stdout);
+    assert_eq!(profile.settings.latency, LatencyDistribution::Uniform { min: Duration::from_millis(10), max: Duration::from_millis(30) });
+    assert_eq!(profile.settings.link_drop_rates.get(&4), Some(&0.5));
+
+    assert!(!profile.filter.is_allowed(5, PacketKind::MsgFragment));
+    assert!(profile.filter.is_allowed(4, PacketKind::MsgFragment));
+
+    let faults = profile.schedule.faults();
+    assert_eq!(faults.len(), 1);
+    assert_eq!(faults[0].at, Duration::from_secs(3));
+    assert!(matches!(faults[0].action, FaultAction::SetPacketDropRate(pdr) if pdr == 0.8));
+}
+
+#[test]
+fn loads_json_profiles() {
+    let json = r#"{
+        "rustable_drone": {
+            "3": { "settings": { "latency": { "Fixed": "20ms" } } },
+            "4": {}
+        }
+    }"#;
+    let profiles = profiles_from_json(json).expect("the JSON profiles should be valid");
+    assert_eq!(profiles.len(), 2);
+    assert_eq!(profiles[&3].settings.latency, LatencyDistribution::Fixed(Duration::from_millis(20)));
+    assert_eq!(profiles[&4].settings.latency, LatencyDistribution::default());
+}
+
+#[test]
+fn loads_files_by_extension() {
+    let dir = std::env::temp_dir().join(format!("rustable_drone_config_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let toml_path = dir.join("network.toml");
+    std::fs::write(&toml_path, readme_example()).unwrap();
+    assert!(load_profiles(&toml_path).unwrap().contains_key(&3));
+
+    let json_path = dir.join("network.json");
+    std::fs::write(&json_path, r#"{ "rustable_drone": { "7": {} } }"#).unwrap();
+    assert!(load_profiles(&json_path).unwrap().contains_key(&7));
+
+    assert!(matches!(load_profiles(dir.join("missing.toml")), Err(ConfigError::Io(_))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_invalid_node_ids() {
+    for key in ["drone", "-1", "256"] {
+        let toml = format!("[rustable_drone.\"{}\".settings]\nlog_to_stdout = true\n", key);
+        let error = profiles_from_toml(&toml).expect_err("the key should be rejected");
+        assert!(matches!(error, ConfigError::Toml(_)));
+        assert!(error.to_string().contains("invalid NodeId"), "unexpected error: {}", error);
+
+        let json = format!(r#"{{ "rustable_drone": {{ "{}": {{}} }} }}"#, key);
+        let error = profiles_from_json(&json).expect_err("the key should be rejected");
+        assert!(matches!(error, ConfigError::Json(_)));
+        assert!(error.to_string().contains("invalid NodeId"), "unexpected error: {}", error);
+    }
+}
+
+#[test]
+fn rejects_invalid_durations() {
+    let toml = "[rustable_drone.3.settings]\nlatency = { Fixed = \"3 parsecs\" }\n";
+    assert!(matches!(profiles_from_toml(toml), Err(ConfigError::Toml(_))));
+
+    let toml = "[[rustable_drone.3.schedule]]\nat = 3\naction = { SetPacketDropRate = 0.8 }\n";
+    assert!(matches!(profiles_from_toml(toml), Err(ConfigError::Toml(_))));
+
+    let json = r#"{ "rustable_drone": { "3": { "schedule": [{ "at": "soon", "action": { "SetPacketDropRate": 0.8 } }] } } }"#;
+    assert!(matches!(profiles_from_json(json), Err(ConfigError::Json(_))));
+}
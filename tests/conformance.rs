@@ -0,0 +1,88 @@
+use rustable_drone::testing::conformance;
+use rustable_drone::RustableDrone;
+
+#[test]
+fn forwards_fragment() {
+    conformance::forwards_fragment::<RustableDrone>();
+}
+
+#[test]
+fn nacks_unexpected_recipient() {
+    conformance::nacks_unexpected_recipient::<RustableDrone>();
+}
+
+#[test]
+fn nacks_destination_is_drone() {
+    conformance::nacks_destination_is_drone::<RustableDrone>();
+}
+
+#[test]
+fn nacks_error_in_routing() {
+    conformance::nacks_error_in_routing::<RustableDrone>();
+}
+
+#[test]
+fn nacks_dropped() {
+    conformance::nacks_dropped::<RustableDrone>();
+}
+
+#[test]
+fn shortcuts_nack_to_removed_sender() {
+    conformance::shortcuts_nack_to_removed_sender::<RustableDrone>();
+}
+
+#[test]
+fn forwards_ack_and_nack() {
+    conformance::forwards_ack_and_nack::<RustableDrone>();
+}
+
+#[test]
+fn shortcuts_undeliverable_ack_and_nack() {
+    conformance::shortcuts_undeliverable_ack_and_nack::<RustableDrone>();
+}
+
+#[test]
+fn shortcuts_only_deliverable_ack_and_nack() {
+    conformance::shortcuts_only_deliverable_ack_and_nack::<RustableDrone>();
+}
+
+#[test]
+fn forwards_flood_request() {
+    conformance::forwards_flood_request::<RustableDrone>();
+}
+
+#[test]
+fn answers_visited_flood_request() {
+    conformance::answers_visited_flood_request::<RustableDrone>();
+}
+
+#[test]
+fn answers_flood_request_without_other_neighbors() {
+    conformance::answers_flood_request_without_other_neighbors::<RustableDrone>();
+}
+
+#[test]
+fn forwards_flood_response() {
+    conformance::forwards_flood_response::<RustableDrone>();
+}
+
+#[test]
+fn shortcuts_undeliverable_flood_response() {
+    conformance::shortcuts_undeliverable_flood_response::<RustableDrone>();
+}
+
+#[test]
+fn crashing_drone() {
+    conformance::crashing_drone::<RustableDrone>();
+}
+
+#[test]
+fn follows_commands() {
+    conformance::follows_commands::<RustableDrone>();
+}
+
+#[test]
+fn passes_the_whole_suite() {
+    //the entry point other implementations use
+    conformance::run_all::<RustableDrone>();
+}
@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use crossbeam_channel::{Receiver, Sender};
+use rustable_drone::testing::{flood_request, fragment, TopologyBuilder, RECV_TIMEOUT};
+use rustable_drone::{FaultAction, FaultSchedule, FilterRule, LatencyDistribution, NackKind, PacketKind, Reorder, RuleAction, RuleMatch, RustableCommand, RustableDrone};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{FloodRequest, NackType, NodeType, Packet, PacketType};
+
+#[test]
+fn fragment_crosses_a_chain_of_drones() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .drone(12, 0.0)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build();
+
+    assert!(topology.node(1).send(fragment(&[1, 11, 12, 2], 7, 0)));
+
+    let packet = topology.node(2).recv().expect("the server should receive the fragment");
+    assert_eq!(packet.routing_header.hop_index, 3);
+    assert_eq!(packet.routing_header.hops, vec![1, 11, 12, 2]);
+    assert_eq!(packet.session_id, 7);
+    assert!(matches!(packet.pack_type, PacketType::MsgFragment(_)));
+
+    for id in [11, 12] {
+        let event = topology.drone(id).recv_event().expect("every drone should notify the controller");
+        assert!(matches!(event, DroneEvent::PacketSent(_)));
+    }
+    assert!(topology.node(1).recv_timeout(Duration::from_millis(50)).is_none());
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn dropped_fragment_is_nacked_back_to_the_client() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 1.0)
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    topology.node(1).send(fragment(&[1, 11, 2], 0, 3));
+
+    let packet = topology.node(1).recv().expect("the client should receive a NACK");
+    let PacketType::Nack(nack) = packet.pack_type else { panic!("expected a NACK, got {:?}", packet.pack_type) };
+    assert_eq!(nack.fragment_index, 3);
+    assert_eq!(nack.nack_type, NackType::Dropped);
+    assert!(topology.node(2).recv_timeout(Duration::from_millis(50)).is_none());
+
+    let events = [topology.drone(11).recv_event(), topology.drone(11).recv_event()];
+    assert!(events.iter().any(|event| matches!(event, Some(DroneEvent::PacketDropped(_)))));
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn configured_drone_filters_packets() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, |mut drone: RustableDrone| {
+            drone.filter.add(1);
+            drone
+        })
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    topology.node(1).send(fragment(&[1, 11, 2], 0, 0));
+
+    assert!(topology.node(2).recv_timeout(Duration::from_millis(200)).is_none());
+    assert!(matches!(topology.drone(11).recv_event(), Some(DroneEvent::PacketDropped(_))));
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn drop_rule_nacks_when_filtered_packets_are_nacked() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, |mut drone: RustableDrone| {
+            drone.settings.send_nack_on_filtered_packet = true;
+            drone.filter.add_rule(FilterRule { id: 1, matcher: RuleMatch { previous_hop: Some(1), ..Default::default() }, action: RuleAction::Drop });
+            drone
+        })
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    topology.node(1).send(fragment(&[1, 11, 2], 0, 2));
+
+    let packet = topology.node(1).recv().expect("the client should receive a NACK");
+    let PacketType::Nack(nack) = packet.pack_type else { panic!("expected a NACK, got {:?}", packet.pack_type) };
+    assert_eq!(nack.fragment_index, 2);
+    assert_eq!(nack.nack_type, NackType::Dropped);
+    assert!(topology.node(2).recv_timeout(Duration::from_millis(50)).is_none());
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn huge_delay_rule_holds_packets_without_panicking() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, |mut drone: RustableDrone| {
+            drone.filter.add_rule(FilterRule { id: 1, matcher: RuleMatch::default(), action: RuleAction::Delay(Duration::MAX) });
+            drone
+        })
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    topology.node(1).send(fragment(&[1, 11, 2], 0, 0));
+
+    assert!(topology.node(2).recv_timeout(Duration::from_millis(100)).is_none());
+    assert!(!topology.drone(11).is_finished());
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn huge_fault_time_is_scheduled_without_panicking() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, |drone: RustableDrone| {
+            let (commands, command_recv) = crossbeam_channel::unbounded();
+            let schedule = FaultSchedule::default().at(Duration::MAX, FaultAction::SetPacketDropRate(1.0));
+            commands.send(RustableCommand::SetSchedule(schedule)).unwrap();
+            drone.with_command_channel(command_recv)
+        })
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    //the fault never happens, fragments keep being forwarded
+    topology.node(1).send(fragment(&[1, 11, 2], 0, 0));
+    assert!(topology.node(2).recv().is_some());
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn crashed_drone_stops_and_is_removed_from_its_neighbors() {
+    let mut topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .drone(12, 0.0)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build();
+
+    topology.crash(12);
+    assert_eq!(topology.neighbors(11).collect::<Vec<_>>(), vec![1]);
+
+    //the drone has nothing left to wait for once every sender is removed
+    let mut finished = false;
+    for _ in 0..100 {
+        finished = topology.drone(12).is_finished();
+        if finished {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(finished);
+
+    //its former neighbor can't reach it anymore
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 0, 0));
+    let packet = topology.node(1).recv().expect("the client should receive a NACK");
+    let PacketType::Nack(nack) = packet.pack_type else { panic!("expected a NACK, got {:?}", packet.pack_type) };
+    assert_eq!(nack.nack_type, NackType::ErrorInRouting(12));
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn flood_request_is_forwarded_along_its_path_trace() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .drone(12, 0.0)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build();
+
+    topology.node(1).flood(7, 5);
+
+    let packet = topology.node(2).recv().expect("the server should receive the FloodRequest");
+    assert_eq!(packet.routing_header, SourceRoutingHeader { hop_index: 3, hops: vec![1, 11, 12, 2] });
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn flood_request_without_its_initiator_is_answered() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .edge(1, 11)
+        .build();
+
+    //the client left itself out of the path trace
+    topology.node(1).send_to(11, Packet {
+        routing_header: SourceRoutingHeader { hop_index: 0, hops: Vec::new() },
+        session_id: 5,
+        pack_type: PacketType::FloodRequest(FloodRequest { flood_id: 7, initiator_id: 1, path_trace: Vec::new() })
+    });
+
+    let packet = topology.node(1).recv().expect("the client should receive a FloodResponse");
+    assert_eq!(packet.routing_header, SourceRoutingHeader { hop_index: 1, hops: vec![11, 1] });
+    let PacketType::FloodResponse(response) = packet.pack_type else { panic!("expected a FloodResponse, got {:?}", packet.pack_type) };
+    assert_eq!(response.path_trace, vec![(1, NodeType::Client), (11, NodeType::Drone)]);
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn flood_response_to_an_unreachable_sender_is_shortcut() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .edge(1, 11)
+        .build();
+
+    //node 13 is not a neighbor of the drone, which sees the request twice
+    let path_trace = vec![(1, NodeType::Client), (13, NodeType::Drone)];
+    topology.drone(11).inject(flood_request(7, 5, path_trace.clone()));
+    assert!(topology.node(1).recv().is_some(), "the client should receive the FloodRequest");
+    topology.drone(11).inject(flood_request(7, 5, path_trace));
+
+    let shortcut = (0..3).find_map(|_| match topology.drone(11).recv_event() {
+        Some(DroneEvent::ControllerShortcut(packet)) => Some(packet),
+        _ => None
+    });
+    let packet = shortcut.expect("the FloodResponse should be sent through the controller");
+    assert_eq!(packet.routing_header, SourceRoutingHeader { hop_index: 1, hops: vec![11, 13, 1] });
+    assert!(matches!(packet.pack_type, PacketType::FloodResponse(_)));
+    assert!(topology.node(1).recv_timeout(Duration::from_millis(50)).is_none());
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn held_fragment_to_a_removed_neighbor_is_nacked() {
+    let mut topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, |mut drone: RustableDrone| {
+            drone.settings.latency = LatencyDistribution::Fixed(Duration::from_millis(200));
+            drone
+        })
+        .drone(12, 0.0)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build();
+
+    //the next hop crashes while the fragment is held
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 0, 3));
+    topology.crash(12);
+
+    let packet = topology.node(1).recv().expect("the client should receive a NACK");
+    let PacketType::Nack(nack) = packet.pack_type else { panic!("expected a NACK, got {:?}", packet.pack_type) };
+    assert_eq!(nack.fragment_index, 3);
+    assert_eq!(nack.nack_type, NackType::ErrorInRouting(12));
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn crashing_drone_nacks_fragments_of_removed_senders_through_the_controller() {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .drone(12, 0.0)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build();
+
+    topology.drone(11).command(DroneCommand::Crash);
+    topology.drone(11).command(DroneCommand::RemoveSender(1));
+    std::thread::sleep(Duration::from_millis(100));
+
+    //a fragment sent by the client before its channel was removed
+    topology.drone(11).inject(fragment(&[1, 11, 12, 2], 0, 3));
+
+    let shortcut = (0..3).find_map(|_| match topology.drone(11).recv_event() {
+        Some(DroneEvent::ControllerShortcut(packet)) => Some(packet),
+        _ => None
+    });
+    let packet = shortcut.expect("the NACK should be sent through the controller");
+    assert_eq!(packet.routing_header, SourceRoutingHeader { hop_index: 1, hops: vec![11, 1] });
+    let PacketType::Nack(nack) = packet.pack_type else { panic!("expected a NACK, got {:?}", packet.pack_type) };
+    assert_eq!(nack.nack_type, NackType::ErrorInRouting(11));
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn crashed_drone_reports_held_packets_before_stopping() {
+    let mut topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, |mut drone: RustableDrone| {
+            drone.settings.latency = LatencyDistribution::Fixed(Duration::from_secs(60));
+            drone
+        })
+        .drone(12, 0.0)
+        .server(2)
+        .edges([(1, 11), (11, 12), (12, 2)])
+        .build();
+
+    topology.node(1).send(fragment(&[1, 11, 12, 2], 0, 3));
+    std::thread::sleep(Duration::from_millis(100));
+    topology.crash(11);
+    for _ in 0..100 {
+        if topology.drone(11).is_finished() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    //the fragment can't reach node 12 anymore, and its NACK can't reach the client
+    let events = topology.drone(11).drain_events();
+    let shortcut = events.into_iter().find_map(|event| match event {
+        DroneEvent::ControllerShortcut(packet) => Some(packet),
+        _ => None
+    });
+    let packet = shortcut.expect("the held fragment should be nacked through the controller");
+    let PacketType::Nack(nack) = packet.pack_type else { panic!("expected a NACK, got {:?}", packet.pack_type) };
+    assert_eq!(nack.fragment_index, 3);
+    assert_eq!(nack.nack_type, NackType::ErrorInRouting(12));
+    assert!(topology.shutdown().is_clean());
+}
+
+#[test]
+fn crashing_drone_answers_stats_queries() {
+    let (commands, command_recv) = crossbeam_channel::unbounded();
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, move |drone: RustableDrone| drone.with_command_channel(command_recv))
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    topology.drone(11).command(DroneCommand::Crash);
+    topology.node(1).send(fragment(&[1, 11, 2], 0, 3));
+    assert!(topology.node(1).recv().is_some(), "the crashing drone should nack the fragment");
+
+    let (reply, stats) = crossbeam_channel::unbounded();
+    commands.send(RustableCommand::QueryStats(reply)).unwrap();
+    let stats = stats.recv_timeout(RECV_TIMEOUT).expect("the crashing drone should answer QueryStats");
+    assert_eq!(stats.kind(PacketKind::MsgFragment).received, 1);
+    assert_eq!(stats.nacks_of(NackKind::ErrorInRouting), 1);
+    assert!(topology.shutdown().is_clean());
+}
+
+/// sends fragments 0..20 through a seeded drone that duplicates and reorders them, and returns their indexes in arrival order
+fn disordered_delivery(seed: u64, duplicate_probability: f32, reorder: Option<Reorder>) -> Vec<u64> {
+    let topology = TopologyBuilder::new()
+        .client(1)
+        .drone_with(11, 0.0, move |mut drone: RustableDrone| {
+            drone.settings.seed = Some(seed);
+            drone.settings.duplicate_probability = duplicate_probability;
+            drone.settings.reorder = reorder;
+            drone
+        })
+        .server(2)
+        .edges([(1, 11), (11, 2)])
+        .build();
+
+    for fragment_index in 0..20 {
+        topology.node(1).send(fragment(&[1, 11, 2], 0, fragment_index));
+    }
+    let arrived = std::iter::from_fn(|| topology.node(2).recv_timeout(Duration::from_millis(200)))
+        .map(|packet| match packet.pack_type {
+            PacketType::MsgFragment(fragment) => fragment.fragment_index,
+            other => panic!("expected a fragment, got {:?}", other)
+        })
+        .collect();
+    assert!(topology.shutdown().is_clean());
+    arrived
+}
+
+#[test]
+fn seeded_drone_duplicates_the_same_fragments() {
+    let arrived = disordered_delivery(42, 0.5, None);
+    assert!(arrived.len() > 20 && arrived.len() < 40, "some fragments should be duplicated, not all of them: {:?}", arrived);
+    assert!((0..20).all(|fragment_index| arrived.contains(&fragment_index)));
+
+    //without reordering the copies follow each other, and the seed picks the same fragments
+    assert!(arrived.is_sorted());
+    assert_eq!(disordered_delivery(42, 0.5, None), arrived);
+    assert_ne!(disordered_delivery(7, 0.5, None), arrived);
+}
+
+#[test]
+fn seeded_drone_reorders_fragments() {
+    let reorder = Some(Reorder { probability: 1.0, max_hold: Duration::from_millis(50) });
+
+    let mut arrived = disordered_delivery(42, 0.0, reorder);
+    assert_eq!(arrived.len(), 20);
+    assert!(!arrived.is_sorted(), "the held back fragments should arrive out of order");
+    arrived.sort_unstable();
+    assert_eq!(arrived, (0..20).collect::<Vec<u64>>());
+
+    //every copy of a duplicated fragment is held back on its own
+    let mut arrived = disordered_delivery(42, 1.0, reorder);
+    assert_eq!(arrived.len(), 40);
+    arrived.sort_unstable();
+    assert_eq!(arrived, (0..20).flat_map(|fragment_index| [fragment_index, fragment_index]).collect::<Vec<u64>>());
+}
+
+/// A drone whose run() never returns
+struct StuckDrone;
+
+impl Drone for StuckDrone {
+    fn new(_id: NodeId, _controller_send: Sender<DroneEvent>, _controller_recv: Receiver<DroneCommand>, _packet_recv: Receiver<Packet>, _packet_send: HashMap<NodeId, Sender<Packet>>, _pdr: f32) -> Self {
+        StuckDrone
+    }
+
+    fn run(&mut self) {
+        loop {
+            std::thread::sleep(Duration::from_secs(60));
+        }
+    }
+}
+
+#[test]
+fn shutdown_reports_drones_that_do_not_stop() {
+    let topology = TopologyBuilder::<StuckDrone>::default()
+        .client(1)
+        .drone(11, 0.0)
+        .edge(1, 11)
+        .build();
+
+    let report = topology.shutdown();
+    assert_eq!(report.stuck, vec![11]);
+    assert!(report.panicked.is_empty());
+    assert!(!report.is_clean());
+}